@@ -0,0 +1,683 @@
+use dat::*;
+use mat::*;
+use ent::*;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::f64::consts::PI;
+
+const TOLERANCE : f64 = 1e-4;
+const MAX_ITER : usize = 100;
+const DRAG_WEIGHT : f64 = 1e-2;
+const RANK_EPS : f64 = 1e-6;
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub enum Constr {
+	Hor(ID, ID),
+	Ver(ID, ID),
+	Dist(ID, ID, f32),
+	Coincident(ID, ID),
+	PointOnLine(ID, ID),
+	PointOnCircle(ID, ID),
+	Parallel(ID, ID),
+	Perp(ID, ID),
+	Tangent(ID, ID),
+	EqualLength(ID, ID),
+	EqualRadius(ID, ID),
+	Midpoint(ID, ID),
+	Symmetric(ID, ID, ID),
+	Concentric(ID, ID),
+	Fixed(ID, f32, f32),
+	Angle(ID, ID, f32),
+	Radius(ID, f32),
+	Diameter(ID, f32),
+	HDist(ID, ID, f32),
+	VDist(ID, ID, f32),
+}
+impl Constr {
+	fn vars(&self, s: &Sys) -> Vec<Var> {
+		match *self {
+		Constr::Hor(a, b) | Constr::Ver(a, b) | Constr::Dist(a, b, _) | Constr::Coincident(a, b)
+		| Constr::HDist(a, b, _) | Constr::VDist(a, b, _) => pointvars(&[a, b]),
+		Constr::PointOnLine(p, l) | Constr::Midpoint(p, l) => [pointvars(&[p]), s.linevars(l)].concat(),
+		Constr::PointOnCircle(p, c) => [pointvars(&[p]), s.curvevars(c)].concat(),
+		Constr::Parallel(l, m) | Constr::Perp(l, m) | Constr::EqualLength(l, m) | Constr::Angle(l, m, _) =>
+			[s.linevars(l), s.linevars(m)].concat(),
+		Constr::Tangent(l, c) => [s.linevars(l), s.curvevars(c)].concat(),
+		Constr::EqualRadius(c, d) | Constr::Concentric(c, d) => [s.curvevars(c), s.curvevars(d)].concat(),
+		Constr::Symmetric(p, q, l) => [pointvars(&[p, q]), s.linevars(l)].concat(),
+		Constr::Fixed(p, _, _) => pointvars(&[p]),
+		Constr::Radius(c, _) | Constr::Diameter(c, _) => s.curvevars(c),
+		}
+	}
+	fn residuals(&self, s: &Sys, x: &[f64]) -> Vec<f64> {
+		match *self {
+		Constr::Hor(a, b) => vec![s.y(x, a) - s.y(x, b)],
+		Constr::Ver(a, b) => vec![s.x(x, a) - s.x(x, b)],
+		Constr::Dist(a, b, d) => vec![s.dist(x, a, b) - d as f64],
+		Constr::Coincident(a, b) => vec![s.x(x, a) - s.x(x, b), s.y(x, a) - s.y(x, b)],
+		Constr::PointOnLine(p, l) => vec![s.linedist(x, p, l)],
+		Constr::PointOnCircle(p, c) => vec![s.dist(x, p, s.center(c)) - s.radius(x, c)],
+		Constr::Parallel(l, m) => vec![cross(unit(s.dir(x, l)), unit(s.dir(x, m)))],
+		Constr::Perp(l, m) => vec![dot(unit(s.dir(x, l)), unit(s.dir(x, m)))],
+		Constr::Tangent(l, c) => vec![s.linedist(x, s.center(c), l).abs() - s.radius(x, c)],
+		Constr::EqualLength(l, m) => vec![len(s.dir(x, l)) - len(s.dir(x, m))],
+		Constr::EqualRadius(c, d) => vec![s.radius(x, c) - s.radius(x, d)],
+		Constr::Midpoint(p, l) => {
+			let (a, b) = s.sk.lines[l].ends();
+			vec![2.0 * s.x(x, p) - s.x(x, a) - s.x(x, b), 2.0 * s.y(x, p) - s.y(x, a) - s.y(x, b)]
+		},
+		Constr::Symmetric(p, q, l) => {
+			let (a, _) = s.sk.lines[l].ends();
+			let d = unit(s.dir(x, l));
+			let m = ((s.x(x, p) + s.x(x, q)) / 2.0 - s.x(x, a), (s.y(x, p) + s.y(x, q)) / 2.0 - s.y(x, a));
+			vec![cross(d, m), dot(d, (s.x(x, q) - s.x(x, p), s.y(x, q) - s.y(x, p)))]
+		},
+		Constr::Concentric(c, d) => {
+			let (c, d) = (s.center(c), s.center(d));
+			vec![s.x(x, c) - s.x(x, d), s.y(x, c) - s.y(x, d)]
+		},
+		Constr::Fixed(p, px, py) => vec![s.x(x, p) - px as f64, s.y(x, p) - py as f64],
+		Constr::Angle(l, m, a) => {
+			let (d, e) = (s.dir(x, l), s.dir(x, m));
+			let mut r = cross(d, e).atan2(dot(d, e)) - (a as f64).to_radians();
+			while r > PI {
+				r -= 2.0 * PI;
+			}
+			while r < -PI {
+				r += 2.0 * PI;
+			}
+			vec![r]
+		},
+		Constr::Radius(c, r) => vec![s.radius(x, c) - r as f64],
+		Constr::Diameter(c, d) => vec![2.0 * s.radius(x, c) - d as f64],
+		Constr::HDist(a, b, d) => vec![s.x(x, b) - s.x(x, a) - d as f64],
+		Constr::VDist(a, b, d) => vec![s.y(x, b) - s.y(x, a) - d as f64],
+		}
+	}
+}
+
+impl Constr {
+	pub fn ids(&self) -> Vec<ID> {
+		match *self {
+		Constr::Hor(a, b) | Constr::Ver(a, b) | Constr::Dist(a, b, _) | Constr::Coincident(a, b)
+		| Constr::PointOnLine(a, b) | Constr::PointOnCircle(a, b) | Constr::Parallel(a, b)
+		| Constr::Perp(a, b) | Constr::Tangent(a, b) | Constr::EqualLength(a, b)
+		| Constr::EqualRadius(a, b) | Constr::Midpoint(a, b) | Constr::Concentric(a, b)
+		| Constr::Angle(a, b, _) | Constr::HDist(a, b, _) | Constr::VDist(a, b, _) => vec![a, b],
+		Constr::Symmetric(a, b, c) => vec![a, b, c],
+		Constr::Fixed(a, _, _) | Constr::Radius(a, _) | Constr::Diameter(a, _) => vec![a],
+		}
+	}
+	pub fn value(&self) -> Option<f32> {
+		match *self {
+		Constr::Dist(_, _, d) | Constr::HDist(_, _, d) | Constr::VDist(_, _, d)
+		| Constr::Angle(_, _, d) | Constr::Radius(_, d) | Constr::Diameter(_, d) => Some(d),
+		_ => None,
+		}
+	}
+	pub fn measure(&self, sk: &Sketch) -> Option<f32> {
+		let pts = &sk.points;
+		let radius = |c| match sk.arcs.get(c) {
+			Some(a) => a.radius(pts),
+			None => sk.circles[c].r,
+		};
+		match *self {
+		Constr::Dist(a, b, _) => Some(pts[a].dist(pts[b])),
+		Constr::HDist(a, b, _) => Some(pts[b].x - pts[a].x),
+		Constr::VDist(a, b, _) => Some(pts[b].y - pts[a].y),
+		Constr::Angle(l, m, _) => {
+			let (d, e) = (sk.dir(l), sk.dir(m));
+			Some(d.cross(e).atan2(d.dot(e)).to_degrees())
+		},
+		Constr::Radius(c, _) => Some(radius(c)),
+		Constr::Diameter(c, _) => Some(2.0 * radius(c)),
+		_ => None,
+		}
+	}
+	pub fn set_value(&mut self, v: f32) {
+		match *self {
+		Constr::Dist(_, _, ref mut d) | Constr::HDist(_, _, ref mut d) | Constr::VDist(_, _, ref mut d)
+		| Constr::Angle(_, _, ref mut d) | Constr::Radius(_, ref mut d) | Constr::Diameter(_, ref mut d) => *d = v,
+		_ => (),
+		}
+	}
+}
+
+impl fmt::Display for Constr {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+		Constr::Hor(a, b) => write!(f, "Horizontal({}, {})", a.slot(), b.slot()),
+		Constr::Ver(a, b) => write!(f, "Vertical({}, {})", a.slot(), b.slot()),
+		Constr::Dist(a, b, d) => write!(f, "Distance({}, {}, {})", a.slot(), b.slot(), d),
+		Constr::Coincident(a, b) => write!(f, "Coincident({}, {})", a.slot(), b.slot()),
+		Constr::PointOnLine(a, b) => write!(f, "PointOnLine({}, {})", a.slot(), b.slot()),
+		Constr::PointOnCircle(a, b) => write!(f, "PointOnCircle({}, {})", a.slot(), b.slot()),
+		Constr::Parallel(a, b) => write!(f, "Parallel({}, {})", a.slot(), b.slot()),
+		Constr::Perp(a, b) => write!(f, "Perpendicular({}, {})", a.slot(), b.slot()),
+		Constr::Tangent(a, b) => write!(f, "Tangent({}, {})", a.slot(), b.slot()),
+		Constr::EqualLength(a, b) => write!(f, "EqualLength({}, {})", a.slot(), b.slot()),
+		Constr::EqualRadius(a, b) => write!(f, "EqualRadius({}, {})", a.slot(), b.slot()),
+		Constr::Midpoint(a, b) => write!(f, "Midpoint({}, {})", a.slot(), b.slot()),
+		Constr::Symmetric(a, b, c) => write!(f, "Symmetric({}, {}, {})", a.slot(), b.slot(), c.slot()),
+		Constr::Concentric(a, b) => write!(f, "Concentric({}, {})", a.slot(), b.slot()),
+		Constr::Fixed(a, x, y) => write!(f, "Fixed({}, {}, {})", a.slot(), x, y),
+		Constr::Angle(a, b, d) => write!(f, "Angle({}, {}, {})", a.slot(), b.slot(), d),
+		Constr::Radius(a, d) => write!(f, "Radius({}, {})", a.slot(), d),
+		Constr::Diameter(a, d) => write!(f, "Diameter({}, {})", a.slot(), d),
+		Constr::HDist(a, b, d) => write!(f, "HDistance({}, {}, {})", a.slot(), b.slot(), d),
+		Constr::VDist(a, b, d) => write!(f, "VDistance({}, {}, {})", a.slot(), b.slot(), d),
+		}
+	}
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct Entry {
+	pub c: Constr,
+	pub active: bool,
+	pub driven: bool,
+	pub expr: Option<String>,
+}
+impl Entry {
+	pub fn new(c: Constr) -> Entry {
+		Entry { c, active: true, driven: false, expr: None }
+	}
+	fn driving(&self) -> bool {
+		self.active && !self.driven
+	}
+}
+
+pub type Constrs = Vec<Entry>;
+
+#[derive(Copy,Clone,Hash,Eq,PartialEq,Debug)]
+enum Var {
+	X(ID),
+	Y(ID),
+	R(ID),
+}
+impl Var {
+	fn id(&self) -> ID {
+		match *self {
+		Var::X(id) | Var::Y(id) | Var::R(id) => id,
+		}
+	}
+	fn get(&self, sk: &Sketch) -> f64 {
+		match *self {
+		Var::X(id) => sk.points[id].x as f64,
+		Var::Y(id) => sk.points[id].y as f64,
+		Var::R(id) => sk.circles[id].r as f64,
+		}
+	}
+	fn set(&self, sk: &mut Sketch, v: f64) {
+		match *self {
+		Var::X(id) => sk.points[id].x = v as f32,
+		Var::Y(id) => sk.points[id].y = v as f32,
+		Var::R(id) => sk.circles[id].r = v as f32,
+		}
+	}
+}
+
+fn cross(a: (f64, f64), b: (f64, f64)) -> f64 {
+	a.0 * b.1 - a.1 * b.0
+}
+
+fn dot(a: (f64, f64), b: (f64, f64)) -> f64 {
+	a.0 * b.0 + a.1 * b.1
+}
+
+fn len(a: (f64, f64)) -> f64 {
+	a.0.hypot(a.1)
+}
+
+fn unit(a: (f64, f64)) -> (f64, f64) {
+	let l = len(a);
+	if l == 0.0 {
+		a
+	} else {
+		(a.0 / l, a.1 / l)
+	}
+}
+
+fn pointvars(l: &[ID]) -> Vec<Var> {
+	l.iter().flat_map(|&id| vec![Var::X(id), Var::Y(id)]).collect()
+}
+
+enum Eqn<'a> {
+	Constr(usize, &'a Constr),
+	Arc(Arc),
+	Target(ID, Vec2),
+}
+impl<'a> Eqn<'a> {
+	fn vars(&self, s: &Sys) -> Vec<Var> {
+		let v = match *self {
+			Eqn::Constr(_, c) => c.vars(s),
+			Eqn::Arc(a) => pointvars(&[a.c, a.a, a.b]),
+			Eqn::Target(id, _) => pointvars(&[id]),
+		};
+		v.into_iter().filter(|v| !s.sk.locked(v.id())).collect()
+	}
+	fn residuals(&self, s: &Sys, x: &[f64]) -> Vec<f64> {
+		match *self {
+		Eqn::Constr(_, c) => c.residuals(s, x),
+		Eqn::Arc(a) => vec![s.dist(x, a.c, a.a) - s.dist(x, a.c, a.b)],
+		Eqn::Target(id, p) => vec![
+			DRAG_WEIGHT * (s.x(x, id) - p.x as f64),
+			DRAG_WEIGHT * (s.y(x, id) - p.y as f64),
+		],
+		}
+	}
+}
+
+fn eqns<'a>(sk: &Sketch, constrs: &'a [Entry]) -> Vec<Eqn<'a>> {
+	constrs.iter().enumerate().filter(|(_, e)| e.driving()).map(|(i, e)| Eqn::Constr(i, &e.c))
+		.chain(sk.arcs.iter().map(|(_, &a)| Eqn::Arc(a)))
+		.collect()
+}
+
+struct Sys<'a> {
+	sk: &'a Sketch,
+	eqns: Vec<Eqn<'a>>,
+	vars: Vec<Var>,
+	idx: HashMap<Var, usize>,
+}
+impl<'a> Sys<'a> {
+	fn new(sk: &'a Sketch, eqns: Vec<Eqn<'a>>) -> (Sys<'a>, Vec<f64>) {
+		let mut s = Sys {
+			sk,
+			eqns: Vec::new(),
+			vars: Vec::new(),
+			idx: HashMap::new(),
+		};
+		let mut x = Vec::new();
+		for e in &eqns {
+			for v in e.vars(&s) {
+				if !s.idx.contains_key(&v) {
+					s.idx.insert(v, s.vars.len());
+					s.vars.push(v);
+					x.push(v.get(sk));
+				}
+			}
+		}
+		s.eqns = eqns;
+		(s, x)
+	}
+	fn linevars(&self, l: ID) -> Vec<Var> {
+		let (a, b) = self.sk.lines[l].ends();
+		pointvars(&[a, b])
+	}
+	fn curvevars(&self, c: ID) -> Vec<Var> {
+		match self.sk.arcs.get(c) {
+		Some(a) => pointvars(&[a.c, a.a, a.b]),
+		None => vec![Var::X(self.sk.circles[c].c), Var::Y(self.sk.circles[c].c), Var::R(c)],
+		}
+	}
+	fn center(&self, c: ID) -> ID {
+		match self.sk.arcs.get(c) {
+		Some(a) => a.c,
+		None => self.sk.circles[c].c,
+		}
+	}
+	fn radius(&self, x: &[f64], c: ID) -> f64 {
+		match self.sk.arcs.get(c) {
+		Some(a) => self.dist(x, a.c, a.a),
+		None => x[self.idx[&Var::R(c)]],
+		}
+	}
+	fn dir(&self, x: &[f64], l: ID) -> (f64, f64) {
+		let (a, b) = self.sk.lines[l].ends();
+		(self.x(x, b) - self.x(x, a), self.y(x, b) - self.y(x, a))
+	}
+	fn linedist(&self, x: &[f64], p: ID, l: ID) -> f64 {
+		let (a, _) = self.sk.lines[l].ends();
+		cross(unit(self.dir(x, l)), (self.x(x, p) - self.x(x, a), self.y(x, p) - self.y(x, a)))
+	}
+	fn x(&self, x: &[f64], id: ID) -> f64 {
+		match self.idx.get(&Var::X(id)) {
+		Some(&k) => x[k],
+		None => self.sk.points[id].x as f64,
+		}
+	}
+	fn y(&self, x: &[f64], id: ID) -> f64 {
+		match self.idx.get(&Var::Y(id)) {
+		Some(&k) => x[k],
+		None => self.sk.points[id].y as f64,
+		}
+	}
+	fn dist(&self, x: &[f64], a: ID, b: ID) -> f64 {
+		(self.x(x, a) - self.x(x, b)).hypot(self.y(x, a) - self.y(x, b))
+	}
+	fn residuals(&self, x: &[f64]) -> Vec<f64> {
+		self.eqns.iter().flat_map(|e| e.residuals(self, x)).collect()
+	}
+	fn jacobian(&self, x: &[f64]) -> Mat {
+		let rows : Vec<usize> = self.eqns.iter().map(|e| e.residuals(self, x).len()).collect();
+		let mut j = Mat::new(rows.iter().sum(), self.vars.len());
+		let mut xp = x.to_vec();
+		let mut row = 0;
+		for (e, &n) in self.eqns.iter().zip(&rows) {
+			for v in e.vars(self) {
+				let k = self.idx[&v];
+				let h = 1e-6 * (1.0 + x[k].abs());
+				xp[k] = x[k] + h;
+				let rp = e.residuals(self, &xp);
+				xp[k] = x[k] - h;
+				let rm = e.residuals(self, &xp);
+				xp[k] = x[k];
+				for i in 0..n {
+					j[(row + i, k)] = (rp[i] - rm[i]) / (2.0 * h);
+				}
+			}
+			row += n;
+		}
+		j
+	}
+	fn minimize(&self, mut x: Vec<f64>) -> (Vec<f64>, f64) {
+		let mut r = self.residuals(&x);
+		let mut f = norm2(&r);
+		let mut lambda = 1e-3;
+		for _ in 0..MAX_ITER {
+			if f < TOLERANCE * TOLERANCE {
+				break;
+			}
+			let j = self.jacobian(&x);
+			let jtj = j.ata();
+			let g : Vec<f64> = j.atv(&r).iter().map(|v| -v).collect();
+			let mut improved = false;
+			while lambda < 1e10 {
+				let mut a = jtj.clone();
+				for i in 0..a.rows() {
+					a[(i,i)] += lambda;
+				}
+				if let Some(d) = cholesky_solve(&a, &g) {
+					let xn : Vec<f64> = x.iter().zip(&d).map(|(a, b)| a + b).collect();
+					let rn = self.residuals(&xn);
+					let fnew = norm2(&rn);
+					if fnew < f {
+						x = xn;
+						r = rn;
+						f = fnew;
+						lambda = (lambda / 10.0).max(1e-12);
+						improved = true;
+						break;
+					}
+				}
+				lambda *= 10.0;
+			}
+			if !improved {
+				break;
+			}
+		}
+		(x, f)
+	}
+}
+
+fn store(sk: &mut Sketch, vars: &[Var], x: &[f64]) {
+	for (v, &val) in vars.iter().zip(x) {
+		v.set(sk, val);
+	}
+}
+
+fn norm2(r: &[f64]) -> f64 {
+	r.iter().map(|x| x * x).sum()
+}
+
+pub fn solve(sk: &mut Sketch, constrs: &[Entry]) -> bool {
+	let (vars, x, f) = {
+		let (s, x) = Sys::new(sk, eqns(sk, constrs));
+		let (x, f) = s.minimize(x);
+		(s.vars, x, f)
+	};
+	store(sk, &vars, &x);
+	f < TOLERANCE * TOLERANCE
+}
+
+pub fn solve_drag(sk: &mut Sketch, constrs: &[Entry], targets: &[(ID, Vec2)]) -> bool {
+	let (vars, x) = {
+		let mut e = eqns(sk, constrs);
+		e.extend(targets.iter().map(|&(id, p)| Eqn::Target(id, p)));
+		let (s, x) = Sys::new(sk, e);
+		let (x, _) = s.minimize(x);
+		(s.vars, x)
+	};
+	store(sk, &vars, &x);
+	solve(sk, constrs)
+}
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub enum Status {
+	Under,
+	Full,
+	Redundant,
+	Over,
+}
+
+pub struct Dof {
+	pub total: usize,
+	free: HashMap<ID, usize>,
+	redundant: HashSet<ID>,
+	over: HashSet<ID>,
+}
+impl Dof {
+	pub fn new() -> Dof {
+		Dof { total: 0, free: HashMap::new(), redundant: HashSet::new(), over: HashSet::new() }
+	}
+	pub fn status(&self, id: ID) -> Status {
+		if self.over.contains(&id) {
+			Status::Over
+		} else if self.redundant.contains(&id) {
+			Status::Redundant
+		} else if self.free.get(&id) == Some(&0) {
+			Status::Full
+		} else {
+			Status::Under
+		}
+	}
+	pub fn is_over(&self) -> bool {
+		!self.over.is_empty()
+	}
+	pub fn is_redundant(&self) -> bool {
+		!self.redundant.is_empty()
+	}
+}
+
+pub fn analyze(sk: &Sketch, constrs: &[Entry]) -> Dof {
+	let (s, x) = Sys::new(sk, eqns(sk, constrs));
+	let j = s.jacobian(&x);
+	let (basis, dep) = j.row_basis(RANK_EPS);
+	let mut dof = Dof::new();
+	dof.total = 2 * sk.points.iter().filter(|&(id, _)| !sk.locked(id)).count() + sk.circles.iter().count() - basis.rank();
+	let mut row = 0;
+	for e in &s.eqns {
+		let r = e.residuals(&s, &x);
+		if norm2(&r) >= TOLERANCE * TOLERANCE {
+			dof.over.extend(e.vars(&s).iter().map(Var::id));
+		} else if dep[row..row + r.len()].iter().any(|&d| d) {
+			dof.redundant.extend(e.vars(&s).iter().map(Var::id));
+		}
+		row += r.len();
+	}
+	let groups = sk.points.iter().map(|(id, _)| (id, pointvars(&[id])))
+		.chain(sk.circles.iter().map(|(id, _)| (id, vec![Var::R(id)])));
+	for (id, vars) in groups {
+		if sk.locked(id) {
+			dof.free.insert(id, 0);
+			continue;
+		}
+		let k : Vec<usize> = match vars.iter().map(|v| s.idx.get(v).cloned()).collect() {
+			Some(k) => k,
+			None => continue,
+		};
+		let mut free = Basis::new();
+		for &a in &k {
+			let col : Vec<f64> = k.iter().map(|&b|
+				(if a == b { 1.0 } else { 0.0 }) - basis.vectors().iter().map(|q| q[a] * q[b]).sum::<f64>()
+			).collect();
+			free.add(&col, RANK_EPS);
+		}
+		dof.free.insert(id, free.rank());
+	}
+	dof
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum Check {
+	Ok,
+	Redundant(Vec<usize>),
+	Conflict(Vec<usize>),
+}
+
+pub fn check(sk: &Sketch, constrs: &[Entry], c: Constr) -> Check {
+	let mut e = eqns(sk, constrs);
+	e.push(Eqn::Constr(constrs.len(), &c));
+	let (s, x) = Sys::new(sk, e);
+	let j = s.jacobian(&x);
+	let mut rows = Vec::new();
+	for e in &s.eqns[..s.eqns.len() - 1] {
+		let n = e.residuals(&s, &x).len();
+		let i = match *e {
+			Eqn::Constr(i, _) => Some(i),
+			_ => None,
+		};
+		rows.extend((0..n).map(|_| i));
+	}
+	let mut basis = Basis::new();
+	for i in 0..rows.len() {
+		basis.add(j.row(i), RANK_EPS);
+	}
+	let cvars : HashSet<Var> = c.vars(&s).into_iter().filter(|v| !sk.locked(v.id())).collect();
+	let mut deps = Vec::new();
+	let mut dependent = false;
+	for i in rows.len()..j.rows() {
+		if norm2(j.row(i)) <= RANK_EPS * RANK_EPS {
+			for e in &s.eqns[..s.eqns.len() - 1] {
+				if let Eqn::Constr(k, _) = *e {
+					if e.vars(&s).iter().any(|v| cvars.contains(v)) && !deps.contains(&k) {
+						dependent = true;
+						deps.push(k);
+					}
+				}
+			}
+		} else if let Some(coef) = basis.combination(j.row(i), RANK_EPS) {
+			dependent = true;
+			for (k, &a) in coef.iter().enumerate() {
+				if let Some(r) = rows[k] {
+					if a.abs() > RANK_EPS && !deps.contains(&r) {
+						deps.push(r);
+					}
+				}
+			}
+		}
+	}
+	let (x, f) = s.minimize(x);
+	if f < TOLERANCE * TOLERANCE {
+		if dependent {
+			Check::Redundant(deps)
+		} else {
+			Check::Ok
+		}
+	} else if dependent {
+		Check::Conflict(deps)
+	} else {
+		Check::Conflict(s.eqns.iter().filter_map(|e| match *e {
+			Eqn::Constr(i, c) if i < constrs.len() && norm2(&c.residuals(&s, &x)) >= TOLERANCE * TOLERANCE => Some(i),
+			_ => None,
+		}).collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pt(sk: &mut Sketch, x: f32, y: f32) -> ID {
+		let id = sk.ids.alloc();
+		sk.points.insert(id, Vec2::new(x, y));
+		id
+	}
+
+	fn near(a: Vec2, b: Vec2) -> bool {
+		a.dist(b) < 1e-3
+	}
+
+	fn entries(l: &[Constr]) -> Constrs {
+		l.iter().map(|&c| Entry::new(c)).collect()
+	}
+
+	#[test]
+	fn converges() {
+		let mut sk = Sketch::new();
+		let (a, b, c) = (pt(&mut sk, 0.0, 0.0), pt(&mut sk, 9.0, 1.0), pt(&mut sk, 8.0, 5.0));
+		let constrs = entries(&[
+			Constr::Fixed(a, 0.0, 0.0),
+			Constr::Hor(a, b),
+			Constr::Ver(b, c),
+			Constr::Dist(a, b, 10.0),
+			Constr::Dist(b, c, 4.0),
+		]);
+		assert!(solve(&mut sk, &constrs));
+		assert!(near(sk.points[a], Vec2::zero()));
+		assert!(near(sk.points[b], Vec2::new(10.0, 0.0)));
+		assert!(near(sk.points[c], Vec2::new(10.0, 4.0)));
+	}
+
+	#[test]
+	fn dof_per_point() {
+		let mut sk = Sketch::new();
+		let (a, b, c) = (pt(&mut sk, 0.0, 0.0), pt(&mut sk, 10.0, 0.0), pt(&mut sk, 3.0, 3.0));
+		let constrs = entries(&[Constr::Fixed(a, 0.0, 0.0), Constr::Hor(a, b)]);
+		let dof = analyze(&sk, &constrs);
+		assert_eq!(dof.total, 3);
+		assert_eq!((dof.free[&a], dof.free[&b]), (0, 1));
+		assert_eq!(dof.free.get(&c), None);
+		assert_eq!((dof.status(a), dof.status(b), dof.status(c)), (Status::Full, Status::Under, Status::Under));
+		assert!(!dof.is_over() && !dof.is_redundant());
+	}
+
+	#[test]
+	fn redundant_and_conflict() {
+		let mut sk = Sketch::new();
+		let (a, b, c) = (pt(&mut sk, 0.0, 0.0), pt(&mut sk, 10.0, 0.0), pt(&mut sk, 10.0, 5.0));
+		let constrs = entries(&[Constr::Hor(a, b), Constr::Dist(a, b, 10.0), Constr::Ver(b, c)]);
+		assert!(solve(&mut sk, &constrs));
+		assert_eq!(check(&sk, &constrs, Constr::Hor(b, a)), Check::Redundant(vec![0]));
+		assert_eq!(check(&sk, &constrs, Constr::Dist(a, b, 7.0)), Check::Conflict(vec![1]));
+		assert_eq!(check(&sk, &constrs, Constr::Dist(b, c, 5.0)), Check::Ok);
+
+		let mut over = constrs.clone();
+		over.push(Entry::new(Constr::Hor(b, a)));
+		let dof = analyze(&sk, &over);
+		assert!(dof.is_redundant() && !dof.is_over());
+		assert_eq!(dof.status(a), Status::Redundant);
+		over.push(Entry::new(Constr::Dist(a, b, 7.0)));
+		let dof = analyze(&sk, &over);
+		assert!(dof.is_over());
+		assert_eq!(dof.status(b), Status::Over);
+	}
+
+	#[test]
+	fn drag_around_fixed() {
+		let mut sk = Sketch::new();
+		let (a, b) = (pt(&mut sk, 0.0, 0.0), pt(&mut sk, 5.0, 0.0));
+		let constrs = entries(&[Constr::Fixed(a, 0.0, 0.0), Constr::Dist(a, b, 5.0)]);
+		assert!(solve_drag(&mut sk, &constrs, &[(b, Vec2::new(0.0, 20.0))]));
+		assert!(near(sk.points[a], Vec2::zero()));
+		assert!((sk.points[b].len() - 5.0).abs() < 1e-3);
+		assert!(sk.points[b].dist(Vec2::new(0.0, 5.0)) < 1e-2, "{:?}", sk.points[b]);
+	}
+
+	#[test]
+	fn locked_points_are_constants() {
+		let mut sk = Sketch::new();
+		let (a, b) = (pt(&mut sk, 0.0, 0.0), pt(&mut sk, 10.0, 3.0));
+		sk.construction.insert(a);
+		let constrs = vec![Entry::new(Constr::Hor(a, b)), Entry::new(Constr::Dist(a, b, 5.0))];
+		assert!(solve(&mut sk, &constrs));
+		assert_eq!(sk.points[a], Vec2::new(0.0, 0.0));
+		assert!((sk.points[b].y).abs() < 1e-4 && (sk.points[b].x - 5.0).abs() < 1e-4);
+		let dof = analyze(&sk, &constrs);
+		assert_eq!(dof.total, 0);
+		assert_eq!((dof.status(a), dof.status(b)), (Status::Full, Status::Full));
+		solve_drag(&mut sk, &constrs, &[(a, Vec2::new(7.0, 7.0))]);
+		assert_eq!(sk.points[a], Vec2::new(0.0, 0.0));
+	}
+}
@@ -2,7 +2,7 @@ extern crate imgui_sys;
 use self::imgui_sys::ImVec2;
 use std::ops::*;
 
-#[derive(Copy,Clone,Debug)]
+#[derive(Copy,Clone,Debug,PartialEq)]
 pub struct Vec2 {
 	pub x: f32,
 	pub y: f32,
@@ -20,6 +20,38 @@ impl Vec2 {
 	pub fn dist(&self, v: Vec2) -> f32 {
 		(self.x - v.x).hypot(self.y - v.y)
 	}
+	pub fn angle(&self) -> f32 {
+		self.y.atan2(self.x)
+	}
+	pub fn len(&self) -> f32 {
+		self.x.hypot(self.y)
+	}
+	pub fn norm(&self) -> Vec2 {
+		let l = self.len();
+		if l == 0.0 {
+			*self
+		} else {
+			*self * (1.0 / l)
+		}
+	}
+	pub fn perp(&self) -> Vec2 {
+		Vec2::new(self.y, -self.x)
+	}
+	pub fn dot(&self, v: Vec2) -> f32 {
+		self.x * v.x + self.y * v.y
+	}
+	pub fn cross(&self, v: Vec2) -> f32 {
+		self.x * v.y - self.y * v.x
+	}
+	pub fn dist_seg(&self, a: Vec2, b: Vec2) -> f32 {
+		let d = b - a;
+		let l = d.dot(d);
+		if l == 0.0 {
+			return self.dist(a);
+		}
+		let t = ((*self - a).dot(d) / l).clamp(0.0, 1.0);
+		self.dist(a + d * t)
+	}
 }
 impl std::ops::Add for Vec2 {
 	type Output = Vec2;
@@ -66,9 +98,12 @@ impl Color {
 	pub fn new(r: u8, g: u8, b: u8, a: u8) -> Color {
 		Color{ r, g, b, a }
 	}
-	pub fn to_u32(&self) -> u32 {
+	pub fn to_u32(self) -> u32 {
 		(self.a as u32) << 24 | (self.b as u32) << 16 | (self.g as u32) << 8 | (self.r as u32)
 	}
+	pub fn rgba(self) -> (u8, u8, u8, u8) {
+		(self.r, self.g, self.b, self.a)
+	}
 }
 
 #[derive(Copy, Clone, Hash, Eq, PartialEq, Debug)]
@@ -77,13 +112,6 @@ pub struct ID {
 	gen: u32
 }
 impl ID {
-	pub fn new() -> ID {
-		static mut I: u32 = 0;
-		unsafe {
-			I += 1;
-			ID { slot: I, gen: 0 }
-		}
-	}
 	pub fn slot(&self) -> u32 {
 		self.slot
 	}
@@ -92,33 +120,82 @@ impl ID {
 	}
 }
 
+#[derive(Clone,PartialEq)]
+pub struct IDAlloc {
+	gens: Vec<u32>,
+	free: Vec<u32>
+}
+impl IDAlloc {
+	pub fn new() -> IDAlloc {
+		IDAlloc { gens: Vec::new(), free: Vec::new() }
+	}
+	pub fn alloc(&mut self) -> ID {
+		match self.free.pop() {
+		Some(slot) => ID { slot, gen: self.gens[slot as usize] },
+		None => {
+			self.gens.push(0);
+			ID { slot: self.gens.len() as u32 - 1, gen: 0 }
+		},
+		}
+	}
+	pub fn free(&mut self, key: ID) {
+		let gen = &mut self.gens[key.slot() as usize];
+		if *gen == key.gen() {
+			*gen += 1;
+			self.free.push(key.slot());
+		}
+	}
+}
+
+#[derive(Clone)]
 pub struct IDMap<T> {
-	data: Vec<Option<(u32,T)>>
+	data: Vec<(u32,Option<T>)>
+}
+// equal if they hold the same entries, whatever the state of the empty slots
+impl<T: PartialEq> PartialEq for IDMap<T> {
+	fn eq(&self, o: &IDMap<T>) -> bool {
+		self.iter().eq(o.iter())
+	}
 }
 impl<T> IDMap<T> {
 	pub fn new() -> IDMap<T> {
 		IDMap { data: Vec::new() }
 	}
-	pub fn insert(&mut self, key: ID, val: T) {
+	fn grow(&mut self, key: ID) {
 		if key.slot() as usize >= self.data.len() {
 			let n = key.slot() as usize + 1 - self.data.len();
 			self.data.reserve(n);
-			for i in 0..n {
-				self.data.push(None)
+			for _ in 0..n {
+				self.data.push((0, None))
 			}
 		}
+	}
+	pub fn insert(&mut self, key: ID, val: T) {
+		self.grow(key);
 		let p = &mut self.data[key.slot() as usize];
-		if let Some((gen, _)) = *p {
-			assert!(gen == key.gen());
+		match *p {
+		(gen, Some(_)) => assert!(gen == key.gen()),
+		(gen, None) => assert!(gen <= key.gen()),
 		}
-		*p = Some((key.gen(), val));
+		*p = (key.gen(), Some(val));
+	}
+	// put back an entry under an older generation, for undo
+	pub fn restore(&mut self, key: ID, val: T) {
+		self.grow(key);
+		self.data[key.slot() as usize] = (key.gen(), Some(val));
+	}
+	pub fn remove(&mut self, key: ID) -> Option<T> {
+		self.get(key)?;
+		let p = &mut self.data[key.slot() as usize];
+		p.0 += 1;
+		p.1.take()
 	}
 	pub fn get(&self, key: ID) -> Option<&T> {
 		if key.slot() as usize >= self.data.len() {
 			return None;
 		}
 		match self.data[key.slot() as usize] {
-		Some((s, ref val)) if s == key.gen() => Some(val),
+		(s, Some(ref val)) if s == key.gen() => Some(val),
 		_ => None,
 		}
 	}
@@ -127,7 +204,7 @@ impl<T> IDMap<T> {
 			return None;
 		}
 		match self.data[key.slot() as usize] {
-		Some((s, ref mut val)) if s == key.gen() => Some(val),
+		(s, Some(ref mut val)) if s == key.gen() => Some(val),
 		_ => None,
 		}
 	}
@@ -164,7 +241,7 @@ impl<'a, T> Iterator for IDMapIterator<'a, T> {
 			let p = &self.map.data[self.id as usize];
 			let slot = self.id;
 			self.id += 1;
-			if let Some((gen, ref val)) = *p {
+			if let (gen, Some(ref val)) = *p {
 				return Some((ID{slot, gen}, val))
 			}
 		}
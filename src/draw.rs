@@ -0,0 +1,245 @@
+use dat::*;
+use ent::*;
+use constr::*;
+use hjimgui::*;
+use view::*;
+
+const DIM_OFFSET : f32 = 20.0;
+const ARROW : f32 = 8.0;
+const MIN_GRID : f32 = 6.0;
+
+fn glyphcolor() -> Color {
+	Color::new(200, 100, 0, 255)
+}
+
+fn dimcolor() -> Color {
+	Color::new(0, 0, 200, 255)
+}
+
+fn drivencolor() -> Color {
+	Color::new(90, 90, 140, 255)
+}
+
+fn inactivecolor() -> Color {
+	Color::new(170, 170, 170, 255)
+}
+
+fn minorcolor() -> Color {
+	Color::new(238, 238, 238, 255)
+}
+
+fn majorcolor() -> Color {
+	Color::new(210, 210, 210, 255)
+}
+
+pub fn griddraw(view: &View, size: Vec2, step: f32, major: u32) -> Vec<DrawCmd> {
+	let n = major.max(1) as i64;
+	let s = step * view.zoom;
+	if s * (n as f32) < MIN_GRID {
+		return vec![];
+	}
+	let (step, n) = if s < MIN_GRID { (step * n as f32, 1) } else { (step, n) };
+	let (lo, hi) = (view.world(Vec2::zero()), view.world(size));
+	let mut minor = Vec::new();
+	let mut major = Vec::new();
+	for i in (lo.x / step).floor() as i64..=(hi.x / step).ceil() as i64 {
+		let x = view.screen(Vec2::new(i as f32 * step, 0.0)).x;
+		let l = if i % n == 0 { &mut major } else { &mut minor };
+		l.push((Vec2::new(x, 0.0), Vec2::new(x, size.y)));
+	}
+	for i in (lo.y / step).floor() as i64..=(hi.y / step).ceil() as i64 {
+		let y = view.screen(Vec2::new(0.0, i as f32 * step)).y;
+		let l = if i % n == 0 { &mut major } else { &mut minor };
+		l.push((Vec2::new(0.0, y), Vec2::new(size.x, y)));
+	}
+	let mut r : Vec<DrawCmd> = minor.iter().map(|&(a, b)| DrawCmd::Line(a, b, minorcolor(), 1.0)).collect();
+	r.extend(major.iter().map(|&(a, b)| DrawCmd::Line(a, b, majorcolor(), 1.0)));
+	r
+}
+
+pub fn label(ts: &Measure, p: Vec2, s: &str, col: Color) -> DrawCmd {
+	DrawCmd::Text(p - ts(s) * 0.5, col, s.to_string())
+}
+
+fn arrow(tip: Vec2, dir: Vec2, col: Color) -> DrawCmd {
+	let d = dir.norm();
+	let b = tip - d * ARROW;
+	DrawCmd::TriangleFilled(tip, b + d.perp() * (ARROW / 3.0), b - d.perp() * (ARROW / 3.0), col)
+}
+
+fn midpoint(view: &View, sk: &Sketch, l: ID) -> Vec2 {
+	let l = sk.lines[l];
+	view.screen((sk.points[l.a] + sk.points[l.b]) * 0.5)
+}
+
+fn center(view: &View, sk: &Sketch, c: ID) -> (Vec2, f32) {
+	let (p, r) = match sk.arcs.get(c) {
+		Some(a) => (sk.points[a.c], a.radius(&sk.points)),
+		None => (sk.points[sk.circles[c].c], sk.circles[c].r),
+	};
+	(view.screen(p), r * view.zoom)
+}
+
+fn glyph(ts: &Measure, p: Vec2, s: &str, col: Color) -> DrawCmd {
+	label(ts, p + Vec2::new(10.0, -10.0), s, col)
+}
+
+fn intersect(a: Vec2, d: Vec2, b: Vec2, e: Vec2) -> Option<Vec2> {
+	let c = d.cross(e);
+	if c.abs() < 1e-6 {
+		None
+	} else {
+		Some(a + d * ((b - a).cross(e) / c))
+	}
+}
+
+fn lindim(ts: &Measure, a: Vec2, b: Vec2, n: Vec2, text: &str, col: Color) -> Vec<DrawCmd> {
+	let (a1, b1) = (a + n * DIM_OFFSET, b + n * DIM_OFFSET);
+	vec![
+		DrawCmd::Line(a, a1 + n * 4.0, col, 1.0),
+		DrawCmd::Line(b, b1 + n * 4.0, col, 1.0),
+		DrawCmd::Line(a1, b1, col, 1.0),
+		arrow(a1, a1 - b1, col),
+		arrow(b1, b1 - a1, col),
+		label(ts, (a1 + b1) * 0.5 + n * 8.0, text, col),
+	]
+}
+
+fn anglepos(view: &View, sk: &Sketch, l: ID, m: ID) -> Option<(Vec2, f32, f32)> {
+	let (d, e) = (sk.dir(l), sk.dir(m));
+	let c = intersect(sk.points[sk.lines[l].a], d, sk.points[sk.lines[m].a], e)?;
+	let a0 = d.angle();
+	let mut a1 = e.angle();
+	while a1 < a0 {
+		a1 += 2.0 * std::f32::consts::PI;
+	}
+	Some((view.screen(c), a0, a1))
+}
+
+pub fn dimtext(c: &Constr) -> Option<String> {
+	match *c {
+	Constr::Dist(_, _, d) | Constr::HDist(_, _, d) | Constr::VDist(_, _, d) => Some(format!("{}", d)),
+	Constr::Angle(_, _, d) => Some(format!("{}\u{b0}", d)),
+	Constr::Radius(_, d) => Some(format!("R{}", d)),
+	Constr::Diameter(_, d) => Some(format!("D{}", d)),
+	_ => None,
+	}
+}
+
+fn dimdraw(ts: &Measure, view: &View, sk: &Sketch, c: &Constr, text: &str, col: Color) -> Vec<DrawCmd> {
+	let pt = |id| view.screen(sk.points[id]);
+	match *c {
+	Constr::Dist(a, b, _) => {
+		let (a, b) = (pt(a), pt(b));
+		lindim(ts, a, b, (b - a).norm().perp(), text, col)
+	},
+	Constr::HDist(a, b, _) => {
+		let (a, b) = (pt(a), pt(b));
+		let y = a.y.min(b.y);
+		let mut r = lindim(ts, Vec2::new(a.x, y), Vec2::new(b.x, y), Vec2::new(0.0, -1.0), text, col);
+		r.push(DrawCmd::Line(a, Vec2::new(a.x, y), col, 1.0));
+		r.push(DrawCmd::Line(b, Vec2::new(b.x, y), col, 1.0));
+		r
+	},
+	Constr::VDist(a, b, _) => {
+		let (a, b) = (pt(a), pt(b));
+		let x = a.x.max(b.x);
+		let mut r = lindim(ts, Vec2::new(x, a.y), Vec2::new(x, b.y), Vec2::new(1.0, 0.0), text, col);
+		r.push(DrawCmd::Line(a, Vec2::new(x, a.y), col, 1.0));
+		r.push(DrawCmd::Line(b, Vec2::new(x, b.y), col, 1.0));
+		r
+	},
+	Constr::Angle(l, m, _) => {
+		let (c, a0, a1) = match anglepos(view, sk, l, m) {
+			Some(x) => x,
+			None => return vec![label(ts, midpoint(view, sk, l), text, col)],
+		};
+		let r = 3.0 * DIM_OFFSET;
+		let at = |a: f32| c + Vec2::new(a.cos(), a.sin()) * r;
+		let mid = (a0 + a1) / 2.0;
+		vec![
+			DrawCmd::Arc(c, r, a0, a1, col, 1.0),
+			label(ts, c + Vec2::new(mid.cos(), mid.sin()) * (r + 12.0), text, col),
+			arrow(at(a0), Vec2::new(a0.sin(), -a0.cos()), col),
+			arrow(at(a1), Vec2::new(-a1.sin(), a1.cos()), col),
+		]
+	},
+	Constr::Radius(c, _) => {
+		let (c, r) = center(view, sk, c);
+		let d = Vec2::new(1.0, -1.0).norm();
+		vec![
+			DrawCmd::Line(c, c + d * r, col, 1.0),
+			arrow(c + d * r, d, col),
+			label(ts, c + d * (r + 16.0), text, col),
+		]
+	},
+	Constr::Diameter(c, _) => {
+		let (c, r) = center(view, sk, c);
+		let d = Vec2::new(1.0, -1.0).norm();
+		vec![
+			DrawCmd::Line(c - d * r, c + d * r, col, 1.0),
+			arrow(c + d * r, d, col),
+			arrow(c - d * r, d * -1.0, col),
+			label(ts, c + d * (r + 16.0), text, col),
+		]
+	},
+	_ => vec![],
+	}
+}
+
+fn glyphdraw(ts: &Measure, view: &View, sk: &Sketch, c: &Constr, col: Color) -> Vec<DrawCmd> {
+	let pt = |id| view.screen(sk.points[id]);
+	match *c {
+	Constr::Hor(a, b) => vec![glyph(ts, (pt(a) + pt(b)) * 0.5, "H", col)],
+	Constr::Ver(a, b) => vec![glyph(ts, (pt(a) + pt(b)) * 0.5, "V", col)],
+	Constr::Coincident(a, _) => vec![DrawCmd::Circle(pt(a), 8.0, col, 1.0)],
+	Constr::PointOnLine(p, _) | Constr::PointOnCircle(p, _) => vec![glyph(ts, pt(p), "o", col)],
+	Constr::Parallel(l, m) => vec![glyph(ts, midpoint(view, sk, l), "//", col), glyph(ts, midpoint(view, sk, m), "//", col)],
+	Constr::Perp(l, m) => vec![glyph(ts, midpoint(view, sk, l), "_|_", col), glyph(ts, midpoint(view, sk, m), "_|_", col)],
+	Constr::Tangent(l, _) => vec![glyph(ts, midpoint(view, sk, l), "T", col)],
+	Constr::EqualLength(l, m) => vec![glyph(ts, midpoint(view, sk, l), "=", col), glyph(ts, midpoint(view, sk, m), "=", col)],
+	Constr::EqualRadius(c, d) => {
+		let ((c, r), (d, s)) = (center(view, sk, c), center(view, sk, d));
+		vec![glyph(ts, c - Vec2::new(0.0, r), "=", col), glyph(ts, d - Vec2::new(0.0, s), "=", col)]
+	},
+	Constr::Midpoint(p, _) => vec![glyph(ts, pt(p), "M", col)],
+	Constr::Symmetric(p, q, _) => vec![glyph(ts, pt(p), "S", col), glyph(ts, pt(q), "S", col)],
+	Constr::Concentric(c, _) => vec![glyph(ts, center(view, sk, c).0, "CC", col)],
+	Constr::Fixed(p, _, _) => {
+		let p = pt(p);
+		let d = Vec2::new(8.0, 8.0);
+		vec![DrawCmd::Rect(p - d, p + d, col, 1.0)]
+	},
+	_ => vec![],
+	}
+}
+
+pub fn dimlabel(ts: &Measure, view: &View, sk: &Sketch, c: &Constr) -> Option<(Vec2, Vec2)> {
+	let t = dimtext(c)?;
+	dimdraw(ts, view, sk, c, &t, dimcolor()).into_iter().filter_map(|d| match d {
+		DrawCmd::Text(p, _, s) => Some((p, p + ts(&s))),
+		_ => None,
+	}).next()
+}
+
+pub fn dimgrab(ts: &Measure, view: &View, sk: &Sketch, constrs: &[Entry], p: Vec2) -> Option<usize> {
+	constrs.iter().position(|e| match dimlabel(ts, view, sk, &e.c) {
+		Some((a, b)) if !e.driven => p.x >= a.x && p.x <= b.x && p.y >= a.y && p.y <= b.y,
+		_ => false,
+	})
+}
+
+pub fn constrdraw(ts: &Measure, view: &View, sk: &Sketch, constrs: &[Entry], glyphs: bool, dims: bool) -> Vec<DrawCmd> {
+	let mut r = Vec::new();
+	for e in constrs {
+		let c = &e.c;
+		match dimtext(c) {
+		Some(_) if !dims => (),
+		None if !glyphs => (),
+		Some(t) if e.driven => r.extend(dimdraw(ts, view, sk, c, &format!("({})", t), if e.active { drivencolor() } else { inactivecolor() })),
+		Some(t) => r.extend(dimdraw(ts, view, sk, c, &t, if e.active { dimcolor() } else { inactivecolor() })),
+		None => r.extend(glyphdraw(ts, view, sk, c, if e.active { glyphcolor() } else { inactivecolor() })),
+		}
+	}
+	r
+}
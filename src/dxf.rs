@@ -0,0 +1,359 @@
+use dat::*;
+use ent::*;
+
+use std::collections::HashSet;
+use std::fs;
+use std::fmt::Write;
+
+fn group(s: &mut String, code: u32, v: &str) {
+	writeln!(s, "{:>3}\n{}", code, v).unwrap();
+}
+
+fn coord(s: &mut String, n: u32, p: Vec2) {
+	group(s, 10 + n, &p.x.to_string());
+	group(s, 20 + n, &(0.0 - p.y).to_string());
+	group(s, 30 + n, "0.0");
+}
+
+fn entity(s: &mut String, kind: &str) {
+	group(s, 0, kind);
+	group(s, 8, "0");
+}
+
+pub fn write(sk: &Sketch) -> String {
+	let sk = &sk.solid();
+	let pts = &sk.points;
+	let mut s = String::new();
+	group(&mut s, 0, "SECTION");
+	group(&mut s, 2, "HEADER");
+	group(&mut s, 9, "$ACADVER");
+	group(&mut s, 1, "AC1009");
+	group(&mut s, 0, "ENDSEC");
+	group(&mut s, 0, "SECTION");
+	group(&mut s, 2, "ENTITIES");
+	let mut used = HashSet::new();
+	for (_, l) in sk.lines.iter() {
+		used.extend(&[l.a, l.b]);
+		entity(&mut s, "LINE");
+		coord(&mut s, 0, pts[l.a]);
+		coord(&mut s, 1, pts[l.b]);
+	}
+	for (_, c) in sk.circles.iter() {
+		used.insert(c.c);
+		entity(&mut s, "CIRCLE");
+		coord(&mut s, 0, pts[c.c]);
+		group(&mut s, 40, &c.r.to_string());
+	}
+	for (_, a) in sk.arcs.iter() {
+		used.extend(&[a.c, a.a, a.b]);
+		let (a0, a1) = a.angles(pts);
+		entity(&mut s, "ARC");
+		coord(&mut s, 0, pts[a.c]);
+		group(&mut s, 40, &a.radius(pts).to_string());
+		group(&mut s, 50, &(-a1).to_degrees().to_string());
+		group(&mut s, 51, &(-a0).to_degrees().to_string());
+	}
+	for (id, &p) in pts.iter() {
+		if !used.contains(&id) {
+			entity(&mut s, "POINT");
+			coord(&mut s, 0, p);
+		}
+	}
+	group(&mut s, 0, "ENDSEC");
+	group(&mut s, 0, "EOF");
+	s
+}
+
+pub fn save(path: &str, sk: &Sketch) -> Result<(), String> {
+	fs::write(path, write(sk)).map_err(|e| format!("{}: {}", path, e))
+}
+
+struct Ent {
+	kind: String,
+	vals: Vec<(u32, f32)>,
+}
+impl Ent {
+	fn get(&self, code: u32) -> Result<f32, String> {
+		self.vals.iter().find(|v| v.0 == code).map(|v| v.1).ok_or_else(|| format!("{} missing group {}", self.kind, code))
+	}
+	fn flag(&self, bit: u32) -> bool {
+		self.get(70).is_ok_and(|f| f as u32 & bit != 0)
+	}
+	fn point(&self, n: u32) -> Result<Vec2, String> {
+		Ok(Vec2::new(self.get(10 + n)?, -self.get(20 + n)?))
+	}
+}
+
+fn entities(s: &str) -> Result<Vec<Ent>, String> {
+	let lines : Vec<&str> = s.lines().map(|l| l.trim()).collect();
+	let mut r = Vec::new();
+	let mut section = "";
+	let mut i = 0;
+	while i + 1 < lines.len() {
+		let code : u32 = lines[i].parse().map_err(|_| format!("line {}: bad group code '{}'", i + 1, lines[i]))?;
+		let v = lines[i + 1];
+		i += 2;
+		match code {
+		0 => {
+			if v == "SECTION" || v == "ENDSEC" {
+				section = "";
+			}
+			if section == "ENTITIES" {
+				r.push(Ent { kind: v.to_string(), vals: Vec::new() });
+			}
+		},
+		2 if section.is_empty() => section = v,
+		_ => if let Some(e) = r.last_mut() {
+			if section == "ENTITIES" {
+				if let Ok(x) = v.parse() {
+					e.vals.push((code, x));
+				}
+			}
+		},
+		}
+	}
+	Ok(r)
+}
+
+enum Geo {
+	Point(Vec2),
+	Line(Vec2, Vec2),
+	Circle(Vec2, f32),
+	Arc(Vec2, Vec2, Vec2),
+	Poly(Vec<(Vec2, f32)>, bool),
+}
+
+// LWPOLYLINE keeps every vertex in one entity: each 10 starts a vertex, 20 and 42 fill it in
+fn lwvertices(e: &Ent) -> Vec<(Vec2, f32)> {
+	let mut r : Vec<(Vec2, f32)> = Vec::new();
+	for &(code, v) in &e.vals {
+		match (code, r.last_mut()) {
+		(10, _) => r.push((Vec2::new(v, 0.0), 0.0)),
+		(20, Some(p)) => p.0.y = -v,
+		(42, Some(p)) => p.1 = v,
+		_ => (),
+		}
+	}
+	r
+}
+
+// center of the arc a polyline segment with bulge b traces, counterclockwise in DXF space when b > 0
+fn bulge(p: Vec2, q: Vec2, b: f32) -> Vec2 {
+	let d = q - p;
+	(p + q) * 0.5 - Vec2::new(-d.y, d.x) * ((1.0 - b * b) / (4.0 * b))
+}
+
+fn geo(e: &Ent) -> Result<Option<Geo>, String> {
+	Ok(Some(match e.kind.as_str() {
+	"POINT" => Geo::Point(e.point(0)?),
+	"LINE" => Geo::Line(e.point(0)?, e.point(1)?),
+	"CIRCLE" => Geo::Circle(e.point(0)?, e.get(40)?),
+	"ARC" => {
+		let (c, r) = (e.point(0)?, e.get(40)?);
+		let (s, t) = (e.get(50)?.to_radians(), e.get(51)?.to_radians());
+		Geo::Arc(c, c + Vec2::new(t.cos(), -t.sin()) * r, c + Vec2::new(s.cos(), -s.sin()) * r)
+	},
+	"LWPOLYLINE" => Geo::Poly(lwvertices(e), e.flag(1)),
+	_ => return Ok(None),
+	}))
+}
+
+fn point(sk: &mut Sketch, p: Vec2) -> ID {
+	let id = sk.ids.alloc();
+	sk.points.insert(id, p);
+	id
+}
+
+pub fn read(s: &str, sk: &mut Sketch) -> Result<(usize, usize), String> {
+	let mut l = Vec::new();
+	let mut skipped = 0;
+	let mut it = entities(s)?.into_iter();
+	while let Some(e) = it.next() {
+		if e.kind == "POLYLINE" {
+			let mut v = Vec::new();
+			for x in it.by_ref().take_while(|x| x.kind != "SEQEND") {
+				if x.kind == "VERTEX" {
+					v.push((x.point(0)?, x.get(42).unwrap_or(0.0)));
+				}
+			}
+			l.push(Geo::Poly(v, e.flag(1)));
+			continue;
+		}
+		match geo(&e)? {
+		Some(g) => l.push(g),
+		None => skipped += 1,
+		}
+	}
+	for g in &l {
+		match *g {
+		Geo::Point(p) => {
+			point(sk, p);
+		},
+		Geo::Line(a, b) => {
+			let l = Line { a: point(sk, a), b: point(sk, b) };
+			sk.lines.insert(sk.ids.alloc(), l);
+		},
+		Geo::Circle(c, r) => {
+			let c = Circle { c: point(sk, c), r };
+			sk.circles.insert(sk.ids.alloc(), c);
+		},
+		Geo::Arc(c, a, b) => {
+			let a = Arc { c: point(sk, c), a: point(sk, a), b: point(sk, b) };
+			sk.arcs.insert(sk.ids.alloc(), a);
+		},
+		Geo::Poly(ref v, closed) => {
+			let ids : Vec<ID> = v.iter().map(|&(p, _)| point(sk, p)).collect();
+			let n = if closed { v.len() } else { v.len().saturating_sub(1) };
+			for i in 0..n {
+				let j = (i + 1) % v.len();
+				let (p, q, k) = (v[i].0, v[j].0, v[i].1);
+				if ids[i] == ids[j] || p.dist(q) == 0.0 {
+					continue;
+				}
+				if k.abs() < 1e-6 {
+					sk.lines.insert(sk.ids.alloc(), Line { a: ids[i], b: ids[j] });
+				} else {
+					let (a, b) = if k > 0.0 { (ids[j], ids[i]) } else { (ids[i], ids[j]) };
+					let a = Arc { c: point(sk, bulge(p, q, k)), a, b };
+					sk.arcs.insert(sk.ids.alloc(), a);
+				}
+			}
+		},
+		}
+	}
+	Ok((l.len(), skipped))
+}
+
+pub fn load(path: &str, sk: &mut Sketch) -> Result<(usize, usize), String> {
+	let s = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+	read(&s, sk).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn near(a: Vec2, b: Vec2) -> bool {
+		a.dist(b) < 1e-3
+	}
+
+	fn pt(sk: &mut Sketch, x: f32, y: f32) -> ID {
+		point(sk, Vec2::new(x, y))
+	}
+
+	fn lines(sk: &Sketch) -> Vec<(Vec2, Vec2)> {
+		sk.lines.iter().map(|(_, l)| (sk.points[l.a], sk.points[l.b])).collect()
+	}
+
+	fn circles(sk: &Sketch) -> Vec<(Vec2, f32)> {
+		sk.circles.iter().map(|(_, c)| (sk.points[c.c], c.r)).collect()
+	}
+
+	fn arcs(sk: &Sketch) -> Vec<(Vec2, Vec2, Vec2)> {
+		sk.arcs.iter().map(|(_, a)| (sk.points[a.c], sk.points[a.a], sk.points[a.b])).collect()
+	}
+
+	fn loose(sk: &Sketch) -> Vec<Vec2> {
+		let mut used = HashSet::new();
+		for (_, l) in sk.lines.iter() {
+			used.extend(&[l.a, l.b]);
+		}
+		for (_, c) in sk.circles.iter() {
+			used.insert(c.c);
+		}
+		for (_, a) in sk.arcs.iter() {
+			used.extend(&[a.c, a.a, a.b]);
+		}
+		sk.points.iter().filter(|&(id, _)| !used.contains(&id)).map(|(_, &p)| p).collect()
+	}
+
+	#[test]
+	fn round_trip() {
+		let mut sk = Sketch::new();
+		let (a, b, c) = (pt(&mut sk, 0.0, 0.0), pt(&mut sk, 30.0, 0.0), pt(&mut sk, 30.0, -20.0));
+		sk.lines.insert(sk.ids.alloc(), Line { a, b });
+		sk.lines.insert(sk.ids.alloc(), Line { a: b, b: c });
+		let o = pt(&mut sk, 50.0, 10.0);
+		sk.circles.insert(sk.ids.alloc(), Circle { c: o, r: 12.5 });
+		let (o, p, q) = (pt(&mut sk, -10.0, 5.0), pt(&mut sk, -4.0, 5.0), pt(&mut sk, -10.0, -1.0));
+		sk.arcs.insert(sk.ids.alloc(), Arc { c: o, a: p, b: q });
+		pt(&mut sk, 7.0, 8.0);
+
+		let mut r = Sketch::new();
+		assert_eq!(read(&write(&sk), &mut r), Ok((5, 0)));
+		let (l, m) = (lines(&sk), lines(&r));
+		assert_eq!(l.len(), m.len());
+		for (x, y) in l.iter().zip(&m) {
+			assert!(near(x.0, y.0) && near(x.1, y.1), "{:?} != {:?}", x, y);
+		}
+		let (l, m) = (circles(&sk), circles(&r));
+		assert_eq!(l.len(), m.len());
+		for (x, y) in l.iter().zip(&m) {
+			assert!(near(x.0, y.0) && (x.1 - y.1).abs() < 1e-3, "{:?} != {:?}", x, y);
+		}
+		let (l, m) = (arcs(&sk), arcs(&r));
+		assert_eq!(l.len(), m.len());
+		for (x, y) in l.iter().zip(&m) {
+			assert!(near(x.0, y.0) && near(x.1, y.1) && near(x.2, y.2), "{:?} != {:?}", x, y);
+		}
+		let (l, m) = (loose(&sk), loose(&r));
+		assert_eq!(l.len(), m.len());
+		assert!(near(l[0], m[0]));
+	}
+
+	#[test]
+	fn skips_construction() {
+		let mut sk = Sketch::new();
+		let (a, b, c) = (pt(&mut sk, 0.0, 0.0), pt(&mut sk, 10.0, 0.0), pt(&mut sk, 10.0, 10.0));
+		sk.lines.insert(sk.ids.alloc(), Line { a, b });
+		let l = sk.ids.alloc();
+		sk.lines.insert(l, Line { a: b, b: c });
+		sk.construction.insert(l);
+
+		let mut r = Sketch::new();
+		assert_eq!(read(&write(&sk), &mut r), Ok((1, 0)));
+		assert_eq!(lines(&r).len(), 1);
+		assert!(loose(&r).is_empty());
+	}
+
+	#[test]
+	fn fixture_square() {
+		let mut sk = Sketch::new();
+		assert_eq!(read(include_str!("../tests/fixtures/square.dxf"), &mut sk), Ok((4, 0)));
+		let l = lines(&sk);
+		assert_eq!(l.len(), 4);
+		assert!(near(l[1].0, Vec2::new(40.0, 0.0)) && near(l[1].1, Vec2::new(40.0, -25.5)));
+		assert!(circles(&sk).is_empty() && arcs(&sk).is_empty() && loose(&sk).is_empty());
+	}
+
+	#[test]
+	fn fixture_polylines() {
+		let mut sk = Sketch::new();
+		assert_eq!(read(include_str!("../tests/fixtures/polylines.dxf"), &mut sk), Ok((2, 1)));
+		let l = lines(&sk);
+		assert_eq!(l.len(), 5);
+		assert!(near(l[0].0, Vec2::zero()) && near(l[0].1, Vec2::new(20.0, 0.0)));
+		assert!(near(l[2].0, Vec2::new(0.0, -10.0)) && near(l[2].1, Vec2::zero()));
+		assert!(near(l[4].0, Vec2::new(40.0, 0.0)) && near(l[4].1, Vec2::new(40.0, -5.0)));
+		let a = arcs(&sk);
+		assert_eq!(a.len(), 1);
+		assert!(near(a[0].0, Vec2::new(10.0, -10.0)));
+		assert!(near(a[0].1, Vec2::new(0.0, -10.0)) && near(a[0].2, Vec2::new(20.0, -10.0)));
+		assert!(loose(&sk).is_empty());
+	}
+
+	#[test]
+	fn fixture_shapes() {
+		let mut sk = Sketch::new();
+		assert_eq!(read(include_str!("../tests/fixtures/shapes.dxf"), &mut sk), Ok((3, 1)));
+		let c = circles(&sk);
+		assert_eq!(c.len(), 1);
+		assert!(near(c[0].0, Vec2::new(15.0, 5.0)) && (c[0].1 - 7.5).abs() < 1e-3);
+		let a = arcs(&sk);
+		assert_eq!(a.len(), 1);
+		assert!(near(a[0].0, Vec2::zero()));
+		assert!(near(a[0].1, Vec2::new(0.0, -10.0)) && near(a[0].2, Vec2::new(10.0, 0.0)));
+		assert_eq!(loose(&sk).len(), 1);
+		assert!(near(loose(&sk)[0], Vec2::new(3.0, -4.0)));
+	}
+}
@@ -0,0 +1,155 @@
+use dat::*;
+
+use std::collections::HashSet;
+use std::f32::consts::PI;
+
+pub type Points = IDMap<Vec2>;
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub struct Line {
+	pub a: ID,
+	pub b: ID,
+}
+impl Line {
+	pub fn ends(&self) -> (ID, ID) {
+		(self.a, self.b)
+	}
+}
+pub type Lines = IDMap<Line>;
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub struct Circle {
+	pub c: ID,
+	pub r: f32,
+}
+pub type Circles = IDMap<Circle>;
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub struct Arc {
+	pub c: ID,
+	pub a: ID,
+	pub b: ID,
+}
+impl Arc {
+	pub fn radius(&self, pts: &Points) -> f32 {
+		pts[self.c].dist(pts[self.a])
+	}
+	pub fn angles(&self, pts: &Points) -> (f32, f32) {
+		let c = pts[self.c];
+		let a0 = (pts[self.a] - c).angle();
+		let mut a1 = (pts[self.b] - c).angle();
+		while a1 <= a0 {
+			a1 += 2.0 * PI;
+		}
+		(a0, a1)
+	}
+	pub fn contains_angle(&self, pts: &Points, a: f32) -> bool {
+		let (a0, a1) = self.angles(pts);
+		let mut a = a;
+		while a < a0 {
+			a += 2.0 * PI;
+		}
+		a <= a1
+	}
+}
+pub type Arcs = IDMap<Arc>;
+
+#[derive(Clone,PartialEq)]
+pub struct Sketch {
+	pub points: Points,
+	pub lines: Lines,
+	pub circles: Circles,
+	pub arcs: Arcs,
+	// construction lines and locked points: held fixed by the solver and left out of exports
+	pub construction: HashSet<ID>,
+	pub ids: IDAlloc,
+}
+impl Sketch {
+	pub fn new() -> Sketch {
+		Sketch {
+			points: Points::new(),
+			lines: Lines::new(),
+			circles: Circles::new(),
+			arcs: Arcs::new(),
+			construction: HashSet::new(),
+			ids: IDAlloc::new(),
+		}
+	}
+	pub fn contains(&self, id: ID) -> bool {
+		self.points.get(id).is_some() || self.lines.get(id).is_some()
+			|| self.circles.get(id).is_some() || self.arcs.get(id).is_some()
+	}
+	pub fn delete(&mut self, ids: &HashSet<ID>) -> HashSet<ID> {
+		let mut r : HashSet<ID> = ids.iter().cloned().filter(|&id| self.points.remove(id).is_some()).collect();
+		let lines : Vec<ID> = self.lines.iter()
+			.filter(|&(id, l)| ids.contains(&id) || r.contains(&l.a) || r.contains(&l.b))
+			.map(|(id, _)| id).collect();
+		let circles : Vec<ID> = self.circles.iter()
+			.filter(|&(id, c)| ids.contains(&id) || r.contains(&c.c))
+			.map(|(id, _)| id).collect();
+		let arcs : Vec<ID> = self.arcs.iter()
+			.filter(|&(id, a)| ids.contains(&id) || r.contains(&a.c) || r.contains(&a.a) || r.contains(&a.b))
+			.map(|(id, _)| id).collect();
+		for &id in &lines {
+			self.lines.remove(id);
+		}
+		for &id in &circles {
+			self.circles.remove(id);
+		}
+		for &id in &arcs {
+			self.arcs.remove(id);
+		}
+		r.extend(lines);
+		r.extend(circles);
+		r.extend(arcs);
+		for &id in &r {
+			self.ids.free(id);
+		}
+		self.construction.retain(|id| !r.contains(id));
+		r
+	}
+	pub fn locked(&self, p: ID) -> bool {
+		self.construction.contains(&p)
+	}
+	// copy without construction geometry, keeping locked points other entities still use
+	pub fn solid(&self) -> Sketch {
+		let mut sk = self.clone();
+		for &id in &self.construction {
+			sk.lines.remove(id);
+		}
+		sk.construction.clear();
+		let mut used = HashSet::new();
+		for (_, l) in sk.lines.iter() {
+			used.extend(&[l.a, l.b]);
+		}
+		for (_, c) in sk.circles.iter() {
+			used.insert(c.c);
+		}
+		for (_, a) in sk.arcs.iter() {
+			used.extend(&[a.c, a.a, a.b]);
+		}
+		for &id in &self.construction {
+			let pts = match self.lines.get(id) {
+				Some(l) => vec![l.a, l.b],
+				None => vec![id],
+			};
+			for p in pts {
+				if !used.contains(&p) {
+					sk.points.remove(p);
+				}
+			}
+		}
+		sk
+	}
+	pub fn near(&self, o: &Sketch, eps: f32) -> bool {
+		self.lines == o.lines && self.arcs == o.arcs && self.construction == o.construction && self.ids == o.ids
+			&& self.points.iter().count() == o.points.iter().count()
+			&& self.points.iter().all(|(id, p)| o.points.get(id).is_some_and(|q| p.dist(*q) <= eps))
+			&& self.circles.iter().count() == o.circles.iter().count()
+			&& self.circles.iter().all(|(id, c)| o.circles.get(id).is_some_and(|d| c.c == d.c && (c.r - d.r).abs() <= eps))
+	}
+	pub fn dir(&self, l: ID) -> Vec2 {
+		let l = self.lines[l];
+		self.points[l.b] - self.points[l.a]
+	}
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum Expr {
+	Num(f32),
+	Var(String),
+	Neg(Box<Expr>),
+	Add(Box<Expr>, Box<Expr>),
+	Sub(Box<Expr>, Box<Expr>),
+	Mul(Box<Expr>, Box<Expr>),
+	Div(Box<Expr>, Box<Expr>),
+}
+impl Expr {
+	pub fn eval(&self, env: &HashMap<String, f32>) -> Result<f32, String> {
+		let v = match *self {
+		Expr::Num(v) => v,
+		Expr::Var(ref n) => match env.get(n) {
+			Some(&v) => v,
+			None => return Err(format!("unknown parameter '{}'", n)),
+		},
+		Expr::Neg(ref a) => -a.eval(env)?,
+		Expr::Add(ref a, ref b) => a.eval(env)? + b.eval(env)?,
+		Expr::Sub(ref a, ref b) => a.eval(env)? - b.eval(env)?,
+		Expr::Mul(ref a, ref b) => a.eval(env)? * b.eval(env)?,
+		Expr::Div(ref a, ref b) => a.eval(env)? / b.eval(env)?,
+		};
+		if v.is_finite() {
+			Ok(v)
+		} else {
+			Err("result is not finite".to_string())
+		}
+	}
+	pub fn vars(&self, r: &mut Vec<String>) {
+		match *self {
+		Expr::Num(_) => (),
+		Expr::Var(ref n) => r.push(n.clone()),
+		Expr::Neg(ref a) => a.vars(r),
+		Expr::Add(ref a, ref b) | Expr::Sub(ref a, ref b)
+		| Expr::Mul(ref a, ref b) | Expr::Div(ref a, ref b) => {
+			a.vars(r);
+			b.vars(r);
+		},
+		}
+	}
+}
+
+#[derive(Clone,Debug,PartialEq)]
+enum Tok {
+	Num(f32),
+	Name(String),
+	Op(char),
+}
+
+fn isname(c: char, first: bool) -> bool {
+	c == '_' || c.is_ascii_alphabetic() || (!first && c.is_ascii_digit())
+}
+
+fn lex(s: &str) -> Result<Vec<Tok>, String> {
+	let cs : Vec<char> = s.chars().collect();
+	let mut r = Vec::new();
+	let mut i = 0;
+	while i < cs.len() {
+		let c = cs[i];
+		if c.is_whitespace() {
+			i += 1;
+		} else if c.is_ascii_digit() || c == '.' {
+			let j = i;
+			while i < cs.len() && (cs[i].is_ascii_digit() || cs[i] == '.') {
+				i += 1;
+			}
+			let k = if i + 1 < cs.len() && (cs[i + 1] == '-' || cs[i + 1] == '+') { i + 2 } else { i + 1 };
+			if i < cs.len() && (cs[i] == 'e' || cs[i] == 'E') && k < cs.len() && cs[k].is_ascii_digit() {
+				i = k;
+				while i < cs.len() && cs[i].is_ascii_digit() {
+					i += 1;
+				}
+			}
+			let n : String = cs[j..i].iter().collect();
+			match n.parse() {
+				Ok(v) => r.push(Tok::Num(v)),
+				Err(_) => return Err(format!("bad number '{}'", n)),
+			}
+		} else if isname(c, true) {
+			let j = i;
+			while i < cs.len() && isname(cs[i], false) {
+				i += 1;
+			}
+			r.push(Tok::Name(cs[j..i].iter().collect()));
+		} else if "+-*/()".contains(c) {
+			r.push(Tok::Op(c));
+			i += 1;
+		} else {
+			return Err(format!("unexpected '{}'", c));
+		}
+	}
+	Ok(r)
+}
+
+struct Parser {
+	toks: Vec<Tok>,
+	i: usize,
+}
+impl Parser {
+	fn peek(&self) -> Option<&Tok> {
+		self.toks.get(self.i)
+	}
+	fn eat(&mut self, c: char) -> bool {
+		if self.peek() == Some(&Tok::Op(c)) {
+			self.i += 1;
+			true
+		} else {
+			false
+		}
+	}
+	fn sum(&mut self) -> Result<Expr, String> {
+		let mut a = self.product()?;
+		loop {
+			if self.eat('+') {
+				a = Expr::Add(Box::new(a), Box::new(self.product()?));
+			} else if self.eat('-') {
+				a = Expr::Sub(Box::new(a), Box::new(self.product()?));
+			} else {
+				return Ok(a);
+			}
+		}
+	}
+	fn product(&mut self) -> Result<Expr, String> {
+		let mut a = self.unary()?;
+		loop {
+			if self.eat('*') {
+				a = Expr::Mul(Box::new(a), Box::new(self.unary()?));
+			} else if self.eat('/') {
+				a = Expr::Div(Box::new(a), Box::new(self.unary()?));
+			} else {
+				return Ok(a);
+			}
+		}
+	}
+	fn unary(&mut self) -> Result<Expr, String> {
+		if self.eat('-') {
+			return Ok(Expr::Neg(Box::new(self.unary()?)));
+		}
+		if self.eat('+') {
+			return self.unary();
+		}
+		if self.eat('(') {
+			let a = self.sum()?;
+			if !self.eat(')') {
+				return Err("expected ')'".to_string());
+			}
+			return Ok(a);
+		}
+		let t = self.peek().cloned();
+		self.i += 1;
+		match t {
+		Some(Tok::Num(v)) => Ok(Expr::Num(v)),
+		Some(Tok::Name(n)) => Ok(Expr::Var(n)),
+		Some(Tok::Op(c)) => Err(format!("unexpected '{}'", c)),
+		None => Err("unexpected end of expression".to_string()),
+		}
+	}
+}
+
+pub fn parse(s: &str) -> Result<Expr, String> {
+	let mut p = Parser { toks: lex(s)?, i: 0 };
+	let e = p.sum()?;
+	match p.peek() {
+	None => Ok(e),
+	Some(&Tok::Op(c)) => Err(format!("unexpected '{}'", c)),
+	Some(_) => Err("expected operator".to_string()),
+	}
+}
+
+pub fn isident(s: &str) -> bool {
+	let mut cs = s.chars();
+	match cs.next() {
+	Some(c) if isname(c, true) => cs.all(|c| isname(c, false)),
+	_ => false,
+	}
+}
+
+pub type Params = Vec<(String, String)>;
+
+fn visit(i: usize, exprs: &[Result<Expr, String>], params: &Params, idx: &HashMap<&str, usize>,
+		state: &mut Vec<u8>, env: &mut HashMap<String, f32>, errs: &mut Vec<String>) {
+	if state[i] != 0 {
+		return;
+	}
+	let name = &params[i].0;
+	let e = match exprs[i] {
+		Ok(ref e) => e,
+		Err(ref m) => {
+			state[i] = 2;
+			errs.push(format!("{}: {}", name, m));
+			return;
+		},
+	};
+	state[i] = 1;
+	let mut vs = Vec::new();
+	e.vars(&mut vs);
+	for v in vs {
+		if let Some(&j) = idx.get(v.as_str()) {
+			if state[j] == 1 {
+				errs.push(format!("{}: cycle through '{}'", name, v));
+				state[i] = 2;
+				return;
+			}
+			visit(j, exprs, params, idx, state, env, errs);
+			if !env.contains_key(&v) {
+				errs.push(format!("{}: depends on invalid '{}'", name, v));
+				state[i] = 2;
+				return;
+			}
+		}
+	}
+	state[i] = 2;
+	match e.eval(env) {
+		Ok(v) => {
+			env.insert(name.clone(), v);
+		},
+		Err(m) => errs.push(format!("{}: {}", name, m)),
+	}
+}
+
+pub fn evaluate(params: &Params) -> (HashMap<String, f32>, Vec<String>) {
+	let exprs : Vec<Result<Expr, String>> = params.iter().map(|p| parse(&p.1)).collect();
+	let mut idx = HashMap::new();
+	let mut errs = Vec::new();
+	for (i, p) in params.iter().enumerate() {
+		if idx.contains_key(p.0.as_str()) {
+			errs.push(format!("{}: defined more than once", p.0));
+		} else {
+			idx.insert(p.0.as_str(), i);
+		}
+	}
+	let mut state = vec![0; params.len()];
+	let mut env = HashMap::new();
+	for i in 0..params.len() {
+		if idx[params[i].0.as_str()] == i {
+			visit(i, &exprs, params, &idx, &mut state, &mut env, &mut errs);
+		}
+	}
+	(env, errs)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn params(l: &[(&str, &str)]) -> Params {
+		l.iter().map(|&(n, e)| (n.to_string(), e.to_string())).collect()
+	}
+
+	#[test]
+	fn parses() {
+		let env = HashMap::new();
+		assert_eq!(parse("1 + 2 * (3 - 1) / 4").unwrap().eval(&env), Ok(2.0));
+		assert_eq!(parse("-2.5e1 + 1E+2 + 5e-1").unwrap().eval(&env), Ok(75.5));
+		assert_eq!(parse("1 2").err(), Some("expected operator".to_string()));
+		assert_eq!(parse("(1 + 2").err(), Some("expected ')'".to_string()));
+		assert_eq!(parse("1 +").err(), Some("unexpected end of expression".to_string()));
+		assert_eq!(parse("1 * )").err(), Some("unexpected ')'".to_string()));
+		assert_eq!(parse("1..2").err(), Some("bad number '1..2'".to_string()));
+		assert_eq!(parse("3 % 2").err(), Some("unexpected '%'".to_string()));
+	}
+
+	#[test]
+	fn evaluates_in_dependency_order() {
+		let (env, errs) = evaluate(&params(&[("b", "a * 2"), ("a", "3")]));
+		assert!(errs.is_empty(), "{:?}", errs);
+		assert_eq!((env["a"], env["b"]), (3.0, 6.0));
+	}
+
+	#[test]
+	fn reports_errors() {
+		let (env, errs) = evaluate(&params(&[
+			("a", "b + 1"),
+			("b", "a + 1"),
+			("c", "1 / 0"),
+			("d", "c + 1"),
+			("e", "missing"),
+			("f", "2 +"),
+			("g", "1"),
+			("g", "2"),
+		]));
+		assert_eq!(env.len(), 1);
+		assert_eq!(env["g"], 1.0);
+		for m in &[
+			"cycle through",
+			"c: result is not finite",
+			"d: depends on invalid 'c'",
+			"e: unknown parameter 'missing'",
+			"f: unexpected end of expression",
+			"g: defined more than once",
+		] {
+			assert!(errs.iter().any(|e| e.contains(m)), "no '{}' in {:?}", m, errs);
+		}
+	}
+}
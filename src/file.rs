@@ -0,0 +1,323 @@
+use dat::*;
+use ent::*;
+use constr::*;
+use expr::*;
+
+use std::collections::HashMap;
+use std::fs;
+use std::fmt::Write;
+
+const VERSION : u32 = 2;
+
+pub struct Doc {
+	pub sk: Sketch,
+	pub constrs: Constrs,
+	pub params: Params,
+	pub view: Vec<(String, String)>,
+}
+
+fn kind(c: &Constr) -> (&'static str, Vec<f32>) {
+	match *c {
+	Constr::Hor(..) => ("Horizontal", vec![]),
+	Constr::Ver(..) => ("Vertical", vec![]),
+	Constr::Dist(_, _, d) => ("Distance", vec![d]),
+	Constr::Coincident(..) => ("Coincident", vec![]),
+	Constr::PointOnLine(..) => ("PointOnLine", vec![]),
+	Constr::PointOnCircle(..) => ("PointOnCircle", vec![]),
+	Constr::Parallel(..) => ("Parallel", vec![]),
+	Constr::Perp(..) => ("Perpendicular", vec![]),
+	Constr::Tangent(..) => ("Tangent", vec![]),
+	Constr::EqualLength(..) => ("EqualLength", vec![]),
+	Constr::EqualRadius(..) => ("EqualRadius", vec![]),
+	Constr::Midpoint(..) => ("Midpoint", vec![]),
+	Constr::Symmetric(..) => ("Symmetric", vec![]),
+	Constr::Concentric(..) => ("Concentric", vec![]),
+	Constr::Fixed(_, x, y) => ("Fixed", vec![x, y]),
+	Constr::Angle(_, _, d) => ("Angle", vec![d]),
+	Constr::Radius(_, d) => ("Radius", vec![d]),
+	Constr::Diameter(_, d) => ("Diameter", vec![d]),
+	Constr::HDist(_, _, d) => ("HDistance", vec![d]),
+	Constr::VDist(_, _, d) => ("VDistance", vec![d]),
+	}
+}
+
+fn mkconstr(name: &str, i: &[ID], v: &[f32]) -> Option<Constr> {
+	Some(match (name, i, v) {
+	("Horizontal", &[a, b], &[]) => Constr::Hor(a, b),
+	("Vertical", &[a, b], &[]) => Constr::Ver(a, b),
+	("Distance", &[a, b], &[d]) => Constr::Dist(a, b, d),
+	("Coincident", &[a, b], &[]) => Constr::Coincident(a, b),
+	("PointOnLine", &[a, b], &[]) => Constr::PointOnLine(a, b),
+	("PointOnCircle", &[a, b], &[]) => Constr::PointOnCircle(a, b),
+	("Parallel", &[a, b], &[]) => Constr::Parallel(a, b),
+	("Perpendicular", &[a, b], &[]) => Constr::Perp(a, b),
+	("Tangent", &[a, b], &[]) => Constr::Tangent(a, b),
+	("EqualLength", &[a, b], &[]) => Constr::EqualLength(a, b),
+	("EqualRadius", &[a, b], &[]) => Constr::EqualRadius(a, b),
+	("Midpoint", &[a, b], &[]) => Constr::Midpoint(a, b),
+	("Symmetric", &[a, b, c], &[]) => Constr::Symmetric(a, b, c),
+	("Concentric", &[a, b], &[]) => Constr::Concentric(a, b),
+	("Fixed", &[a], &[x, y]) => Constr::Fixed(a, x, y),
+	("Angle", &[a, b], &[d]) => Constr::Angle(a, b, d),
+	("Radius", &[a], &[d]) => Constr::Radius(a, d),
+	("Diameter", &[a], &[d]) => Constr::Diameter(a, d),
+	("HDistance", &[a, b], &[d]) => Constr::HDist(a, b, d),
+	("VDistance", &[a, b], &[d]) => Constr::VDist(a, b, d),
+	_ => return None,
+	})
+}
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+enum Kind {
+	Point,
+	Line,
+	Curve,
+}
+
+fn sig(name: &str) -> Option<&'static [Kind]> {
+	use self::Kind::*;
+	Some(match name {
+	"Horizontal" | "Vertical" | "Distance" | "Coincident" | "HDistance" | "VDistance" => &[Point, Point],
+	"PointOnLine" | "Midpoint" => &[Point, Line],
+	"PointOnCircle" => &[Point, Curve],
+	"Parallel" | "Perpendicular" | "EqualLength" | "Angle" => &[Line, Line],
+	"Tangent" => &[Line, Curve],
+	"EqualRadius" | "Concentric" => &[Curve, Curve],
+	"Symmetric" => &[Point, Point, Line],
+	"Fixed" => &[Point],
+	"Radius" | "Diameter" => &[Curve],
+	_ => return None,
+	})
+}
+
+fn join<T: ToString>(l: &[T]) -> String {
+	l.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+pub fn write(sk: &Sketch, constrs: &[Entry], params: &Params, view: &[(String, String)]) -> String {
+	let mut s = String::new();
+	writeln!(s, "fed {}", VERSION).unwrap();
+	for (id, p) in sk.points.iter() {
+		writeln!(s, "point {} {} {}", id.slot(), p.x, p.y).unwrap();
+	}
+	for (id, l) in sk.lines.iter() {
+		writeln!(s, "line {} {} {}", id.slot(), l.a.slot(), l.b.slot()).unwrap();
+	}
+	for (id, c) in sk.circles.iter() {
+		writeln!(s, "circle {} {} {}", id.slot(), c.c.slot(), c.r).unwrap();
+	}
+	for (id, a) in sk.arcs.iter() {
+		writeln!(s, "arc {} {} {} {}", id.slot(), a.c.slot(), a.a.slot(), a.b.slot()).unwrap();
+	}
+	let pts = sk.points.iter().map(|(id, _)| id);
+	for id in pts.chain(sk.lines.iter().map(|(id, _)| id)).filter(|id| sk.construction.contains(id)) {
+		writeln!(s, "construction {}", id.slot()).unwrap();
+	}
+	for (n, e) in params {
+		writeln!(s, "param {} = {}", n, e).unwrap();
+	}
+	for e in constrs {
+		let (name, v) = kind(&e.c);
+		let ids : Vec<u32> = e.c.ids().iter().map(|id| id.slot()).collect();
+		write!(s, "constr {} {} {} {} :", e.active as u8, e.driven as u8, name, join(&ids)).unwrap();
+		for x in v {
+			write!(s, " {}", x).unwrap();
+		}
+		match e.expr {
+		Some(ref x) => writeln!(s, " = {}", x).unwrap(),
+		None => writeln!(s).unwrap(),
+		}
+	}
+	for (k, v) in view {
+		writeln!(s, "view {} {}", k, v).unwrap();
+	}
+	s
+}
+
+pub fn save(path: &str, sk: &Sketch, constrs: &[Entry], params: &Params, view: &[(String, String)]) -> Result<(), String> {
+	fs::write(path, write(sk, constrs, params, view)).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn num<T: ::std::str::FromStr>(s: Option<&str>) -> Result<T, String> {
+	match s.map(|s| s.parse()) {
+	Some(Ok(v)) => Ok(v),
+	Some(Err(_)) => Err(format!("bad number '{}'", s.unwrap())),
+	None => Err("missing field".to_string()),
+	}
+}
+
+fn real(s: Option<&str>) -> Result<f32, String> {
+	match num::<f32>(s)? {
+	v if v.is_finite() => Ok(v),
+	_ => Err(format!("bad number '{}'", s.unwrap())),
+	}
+}
+
+struct Reader {
+	slots: HashMap<u32, (ID, Kind)>,
+}
+impl Reader {
+	fn id(&self, s: Option<&str>, k: Kind) -> Result<ID, String> {
+		let n = num(s)?;
+		match self.slots.get(&n) {
+		Some(&(id, j)) if j == k => Ok(id),
+		Some(&(_, j)) => Err(format!("id {} is a {:?}, expected a {:?}", n, j, k)),
+		None => Err(format!("unknown id {}", n)),
+		}
+	}
+}
+
+fn line(l: &str, r: &Reader, doc: &mut Doc) -> Result<(), String> {
+	let (l, tail) = match l.find('=') {
+		Some(i) => (&l[..i], Some(l[i + 1..].trim())),
+		None => (l, None),
+	};
+	let mut t = l.split_whitespace();
+	match t.next() {
+	Some("point") => {
+		let id = r.id(t.next(), Kind::Point)?;
+		let p = Vec2::new(real(t.next())?, real(t.next())?);
+		doc.sk.points.insert(id, p);
+	},
+	Some("line") => {
+		let id = r.id(t.next(), Kind::Line)?;
+		let l = Line { a: r.id(t.next(), Kind::Point)?, b: r.id(t.next(), Kind::Point)? };
+		doc.sk.lines.insert(id, l);
+	},
+	Some("circle") => {
+		let id = r.id(t.next(), Kind::Curve)?;
+		let c = Circle { c: r.id(t.next(), Kind::Point)?, r: real(t.next())? };
+		doc.sk.circles.insert(id, c);
+	},
+	Some("arc") => {
+		let id = r.id(t.next(), Kind::Curve)?;
+		let a = Arc { c: r.id(t.next(), Kind::Point)?, a: r.id(t.next(), Kind::Point)?, b: r.id(t.next(), Kind::Point)? };
+		doc.sk.arcs.insert(id, a);
+	},
+	Some("construction") => {
+		let n = t.next();
+		let id = r.id(n, Kind::Point).or_else(|_| r.id(n, Kind::Line))?;
+		doc.sk.construction.insert(id);
+	},
+	Some("param") => match (t.next(), tail) {
+		(Some(n), Some(e)) if isident(n) => doc.params.push((n.to_string(), e.to_string())),
+		_ => return Err("bad parameter".to_string()),
+	},
+	Some("constr") => {
+		let active = num::<u8>(t.next())? != 0;
+		let driven = num::<u8>(t.next())? != 0;
+		let name = t.next().ok_or("missing constraint kind")?;
+		let kinds = sig(name).ok_or_else(|| format!("bad constraint '{}'", name))?;
+		let l : Vec<&str> = t.by_ref().take_while(|&s| s != ":").collect();
+		if l.len() != kinds.len() {
+			return Err(format!("bad constraint '{}'", name));
+		}
+		let mut ids = Vec::new();
+		let mut v = Vec::new();
+		for (&s, &k) in l.iter().zip(kinds) {
+			ids.push(r.id(Some(s), k)?);
+		}
+		for s in t {
+			v.push(real(Some(s))?);
+		}
+		let c = mkconstr(name, &ids, &v).ok_or_else(|| format!("bad constraint '{}'", name))?;
+		doc.constrs.push(Entry { c, active, driven, expr: tail.map(|s| s.to_string()) });
+	},
+	Some("view") => {
+		let k = t.next().ok_or("missing view key")?;
+		doc.view.push((k.to_string(), join(&t.collect::<Vec<_>>())));
+	},
+	Some(k) => return Err(format!("unknown record '{}'", k)),
+	None => (),
+	}
+	Ok(())
+}
+
+pub fn read(s: &str) -> Result<Doc, String> {
+	let mut lines = s.lines();
+	let mut h = lines.next().unwrap_or("").split_whitespace();
+	if h.next() != Some("fed") {
+		return Err("not a sketch file".to_string());
+	}
+	let v : u32 = num(h.next())?;
+	if v > VERSION {
+		return Err(format!("unsupported version {}", v));
+	}
+	let mut doc = Doc { sk: Sketch::new(), constrs: Constrs::new(), params: Params::new(), view: Vec::new() };
+	let mut used = Vec::new();
+	for l in s.lines().skip(1) {
+		let mut t = l.split_whitespace();
+		let k = match t.next() {
+			Some("point") => Kind::Point,
+			Some("line") => Kind::Line,
+			Some("circle") | Some("arc") => Kind::Curve,
+			_ => continue,
+		};
+		used.push((num::<u32>(t.next())?, k));
+	}
+	let mut r = Reader { slots: HashMap::new() };
+	used.sort_by_key(|u| u.0);
+	for &(n, k) in &used {
+		if r.slots.insert(n, (doc.sk.ids.alloc(), k)).is_some() {
+			return Err(format!("duplicate id {}", n));
+		}
+	}
+	for (i, l) in lines.enumerate() {
+		line(l, &r, &mut doc).map_err(|e| format!("line {}: {}", i + 2, e))?;
+	}
+	Ok(doc)
+}
+
+pub fn load(path: &str) -> Result<Doc, String> {
+	let s = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+	read(&s).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const OK : &str = "fed 1\npoint 0 0 0\npoint 1 10 0\npoint 2 10 10\nline 3 0 1\nline 4 1 2\nconstr 1 0 Perpendicular 3 4 :\n";
+
+	#[test]
+	fn reads_valid() {
+		let doc = read(OK).unwrap();
+		assert_eq!(doc.sk.points.iter().count(), 3);
+		assert_eq!(doc.sk.lines.iter().count(), 2);
+		assert_eq!(doc.constrs.len(), 1);
+	}
+
+	#[test]
+	fn sparse_slots() {
+		let mut doc = read("fed 1\npoint 4294967295 0 0\npoint 100000000 1 0\nline 7 4294967295 100000000\n").unwrap();
+		assert_eq!(doc.sk.points.iter().count(), 2);
+		assert_eq!(doc.sk.lines.iter().count(), 1);
+		assert!(doc.sk.ids.alloc().slot() < 4);
+	}
+
+	#[test]
+	fn keeps_construction() {
+		let doc = read(&format!("{}construction 4\nconstruction 2\n", OK)).unwrap();
+		assert_eq!(doc.sk.construction.len(), 2);
+		let doc = read(&write(&doc.sk, &doc.constrs, &doc.params, &doc.view)).unwrap();
+		assert_eq!(doc.sk.construction.len(), 2);
+		assert!(doc.sk.lines.iter().any(|(id, l)| doc.sk.construction.contains(&id) && doc.sk.points[l.b] == Vec2::new(10.0, 10.0)));
+	}
+
+	#[test]
+	fn rejects_wrong_kind() {
+		let bad = [
+			OK.replace("line 4 1 2", "line 4 1 3"),
+			OK.replace("Perpendicular 3 4", "Perpendicular 0 1"),
+			OK.replace("Perpendicular 3 4 :", "Radius 3 : 5"),
+			OK.replace("Perpendicular 3 4", "Perpendicular 3"),
+			format!("{}circle 5 3 2\n", OK),
+			OK.replace("point 1 10 0", "point 1 NaN inf"),
+			OK.replace("Perpendicular 3 4 :", "Distance 0 1 : inf"),
+			format!("{}circle 5 0 1\nconstruction 5\n", OK),
+		];
+		for s in &bad {
+			assert!(read(s).is_err(), "accepted {:?}", s);
+		}
+	}
+}
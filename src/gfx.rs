@@ -3,6 +3,7 @@ extern crate gl;
 extern crate imgui_sys;
 
 use hjimgui::*;
+use dat::*;
 
 use std::time::Instant;
 
@@ -27,13 +28,15 @@ impl GFX {
 		gl_attr.set_context_version(4, 1);
 	
 		let window = video_subsystem
-			.window("Game", 900, 900)
+			.window("fed", 900, 900)
 			.opengl()
+			.resizable()
+			.allow_highdpi()
 			.build()
 			.unwrap();
 		
 		let gl_context = window.gl_create_context().unwrap();
-		let _gl = gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+		gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
 	
 		window.gl_make_current(&gl_context).unwrap();
 	
@@ -44,16 +47,28 @@ impl GFX {
 	
 	pub fn frame_start(&mut self, imgui: &mut Imgui) -> bool {
 		self.startt = Instant::now();
+		let mut wheel = Vec2::zero();
+		let mut resized = false;
 		for event in self.event_pump.poll_iter() {
 			match event {
 				sdl2::event::Event::Quit {..} => return false,
 				sdl2::event::Event::TextInput { text, .. } =>
 					imgui.add_text(&text),
+				sdl2::event::Event::Window { win_event: sdl2::event::WindowEvent::SizeChanged(..), .. } =>
+					resized = true,
+				sdl2::event::Event::MouseWheel { x, y, direction, .. } => {
+					let s = if direction == sdl2::mouse::MouseWheelDirection::Flipped { -1.0 } else { 1.0 };
+					wheel += Vec2::new(x as f32, y as f32) * s;
+				},
 				_ => ()
 			}
 		}
+		if resized {
+			self.resize(imgui);
+		}
 		let mouse = self.event_pump.mouse_state();
-		imgui.mouse(mouse.x(), mouse.y(), mouse.left(), mouse.right());
+		let buttons = [mouse.left(), mouse.right(), mouse.middle(), mouse.x1(), mouse.x2()];
+		imgui.mouse(mouse.x(), mouse.y(), buttons, wheel);
 		let kb = self.event_pump.keyboard_state();
 		imgui.keyboard(kb, self.sdl.keyboard().mod_state());
 
@@ -61,6 +76,12 @@ impl GFX {
 		true
 	}
 	
+	pub fn resize(&self, imgui: &mut Imgui) {
+		let (w, h) = self.window.size();
+		let (fbw, fbh) = self.window.drawable_size();
+		imgui.resize(w as f32, h as f32, fbw as f32, fbh as f32);
+	}
+	
 	pub fn frame_end(&mut self, imgui: &mut Imgui) {
 		self.window.gl_make_current(&self.gl_context).unwrap();
 		imgui.render();
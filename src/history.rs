@@ -0,0 +1,220 @@
+use dat::*;
+use ent::*;
+use constr::*;
+use expr::*;
+
+use std::collections::VecDeque;
+
+const MAX_UNDO : usize = 200;
+pub const DRIFT : f32 = 1e-4;
+
+#[derive(Clone,PartialEq)]
+pub struct State {
+	pub sk: Sketch,
+	pub constrs: Constrs,
+	pub params: Params,
+}
+
+type Changes<T> = Vec<(ID, Option<T>, Option<T>)>;
+
+fn mapdiff<T: Copy + PartialEq>(a: &IDMap<T>, b: &IDMap<T>) -> Changes<T> {
+	let mut r = Vec::new();
+	for (id, &x) in a.iter() {
+		match b.get(id) {
+		Some(&y) if y == x => (),
+		y => r.push((id, Some(x), y.cloned())),
+		}
+	}
+	for (id, &y) in b.iter() {
+		if a.get(id).is_none() {
+			r.push((id, None, Some(y)));
+		}
+	}
+	r
+}
+
+fn mapapply<T: Copy>(m: &mut IDMap<T>, l: &Changes<T>, undo: bool) {
+	for &(id, x, y) in l {
+		match if undo { x } else { y } {
+		Some(v) => m.restore(id, v),
+		None => {
+			m.remove(id);
+		},
+		}
+	}
+}
+
+// the range of a list that changed: start, old items, new items
+type Splice<T> = (usize, Vec<T>, Vec<T>);
+
+fn listdiff<T: Clone + PartialEq>(a: &[T], b: &[T]) -> Option<Splice<T>> {
+	if a == b {
+		return None;
+	}
+	let p = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+	let s = a[p..].iter().rev().zip(b[p..].iter().rev()).take_while(|(x, y)| x == y).count();
+	Some((p, a[p..a.len() - s].to_vec(), b[p..b.len() - s].to_vec()))
+}
+
+fn listapply<T: Clone>(v: &mut Vec<T>, d: &Option<Splice<T>>, undo: bool) {
+	if let Some((p, ref x, ref y)) = *d {
+		let (from, to) = if undo { (y, x) } else { (x, y) };
+		v.splice(p..p + from.len(), to.iter().cloned());
+	}
+}
+
+// one recorded edit: only what changed, applied backwards to undo and forwards to redo
+pub struct Edit {
+	points: Changes<Vec2>,
+	lines: Changes<Line>,
+	circles: Changes<Circle>,
+	arcs: Changes<Arc>,
+	construction: Vec<(ID, bool)>,
+	ids: Option<(IDAlloc, IDAlloc)>,
+	constrs: Option<Splice<Entry>>,
+	params: Option<Splice<(String, String)>>,
+}
+impl Edit {
+	pub fn new(a: &State, b: &State) -> Edit {
+		let (s, t) = (&a.sk, &b.sk);
+		let mut construction : Vec<(ID, bool)> = s.construction.difference(&t.construction).map(|&id| (id, false)).collect();
+		construction.extend(t.construction.difference(&s.construction).map(|&id| (id, true)));
+		Edit {
+			points: mapdiff(&s.points, &t.points),
+			lines: mapdiff(&s.lines, &t.lines),
+			circles: mapdiff(&s.circles, &t.circles),
+			arcs: mapdiff(&s.arcs, &t.arcs),
+			construction,
+			ids: if s.ids == t.ids { None } else { Some((s.ids.clone(), t.ids.clone())) },
+			constrs: listdiff(&a.constrs, &b.constrs),
+			params: listdiff(&a.params, &b.params),
+		}
+	}
+	fn apply(&self, s: &mut State, undo: bool) {
+		mapapply(&mut s.sk.points, &self.points, undo);
+		mapapply(&mut s.sk.lines, &self.lines, undo);
+		mapapply(&mut s.sk.circles, &self.circles, undo);
+		mapapply(&mut s.sk.arcs, &self.arcs, undo);
+		for &(id, on) in &self.construction {
+			if on != undo {
+				s.sk.construction.insert(id);
+			} else {
+				s.sk.construction.remove(&id);
+			}
+		}
+		if let Some((ref x, ref y)) = self.ids {
+			s.sk.ids = if undo { x.clone() } else { y.clone() };
+		}
+		listapply(&mut s.constrs, &self.constrs, undo);
+		listapply(&mut s.params, &self.params, undo);
+	}
+}
+
+pub struct History {
+	undo: VecDeque<(String, Edit)>,
+	redo: Vec<(String, Edit)>,
+}
+impl History {
+	pub fn new() -> History {
+		History { undo: VecDeque::new(), redo: Vec::new() }
+	}
+	pub fn push(&mut self, name: &str, e: Edit) {
+		self.undo.push_back((name.to_string(), e));
+		if self.undo.len() > MAX_UNDO {
+			self.undo.pop_front();
+		}
+		self.redo.clear();
+	}
+	pub fn undo(&mut self, s: &mut State) -> bool {
+		match self.undo.pop_back() {
+		Some((n, e)) => {
+			e.apply(s, true);
+			self.redo.push((n, e));
+			true
+		},
+		None => false,
+		}
+	}
+	pub fn redo(&mut self, s: &mut State) -> bool {
+		match self.redo.pop() {
+		Some((n, e)) => {
+			e.apply(s, false);
+			self.undo.push_back((n, e));
+			true
+		},
+		None => false,
+		}
+	}
+	pub fn undo_name(&self) -> Option<&str> {
+		self.undo.back().map(|x| x.0.as_str())
+	}
+	pub fn redo_name(&self) -> Option<&str> {
+		self.redo.last().map(|x| x.0.as_str())
+	}
+	pub fn clear(&mut self) {
+		self.undo.clear();
+		self.redo.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn state() -> State {
+		State { sk: Sketch::new(), constrs: Constrs::new(), params: Params::new() }
+	}
+
+	#[test]
+	fn drops_oldest() {
+		let mut h = History::new();
+		let s = state();
+		for i in 0..MAX_UNDO + 5 {
+			h.push(&i.to_string(), Edit::new(&s, &s));
+		}
+		let mut n = 0;
+		while h.undo(&mut state()) {
+			n += 1;
+		}
+		assert_eq!(n, MAX_UNDO);
+		assert_eq!(h.redo_name(), Some("5"));
+	}
+
+	#[test]
+	fn undo_redo_edits() {
+		let mut h = History::new();
+		let mut s = state();
+		let a = s.sk.ids.alloc();
+		s.sk.points.insert(a, Vec2::new(1.0, 2.0));
+		s.params.push(("w".to_string(), "3".to_string()));
+		let s0 = s.clone();
+
+		let mut t = s.clone();
+		let b = t.sk.ids.alloc();
+		t.sk.points.insert(b, Vec2::new(5.0, 2.0));
+		t.sk.points[a] = Vec2::new(0.0, 2.0);
+		t.sk.lines.insert(t.sk.ids.alloc(), Line { a, b });
+		t.sk.construction.insert(b);
+		t.constrs.push(Entry::new(Constr::Hor(a, b)));
+		h.push("Add", Edit::new(&s, &t));
+		let s1 = t.clone();
+
+		let mut u = t.clone();
+		let gone : ::std::collections::HashSet<ID> = [a].iter().cloned().collect();
+		u.sk.delete(&gone);
+		u.constrs.clear();
+		u.params[0].1 = "4".to_string();
+		h.push("Delete", Edit::new(&t, &u));
+		let s2 = u.clone();
+
+		let mut cur = s2.clone();
+		assert!(h.undo(&mut cur));
+		assert!(cur.sk.near(&s1.sk, 0.0) && cur.constrs == s1.constrs && cur.params == s1.params);
+		assert!(h.undo(&mut cur));
+		assert!(cur.sk.near(&s0.sk, 0.0) && cur.constrs == s0.constrs && cur.params == s0.params);
+		assert!(!h.undo(&mut cur));
+		assert!(h.redo(&mut cur) && h.redo(&mut cur));
+		assert!(cur.sk.near(&s2.sk, 0.0) && cur.constrs == s2.constrs && cur.params == s2.params);
+		assert!(!h.redo(&mut cur));
+	}
+}
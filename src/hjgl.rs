@@ -1,10 +1,10 @@
 use gl::types::*;
 use std::ffi::CString;
 
-pub fn refcall<T, U: Fn(*mut T) -> ()>(f: U) -> T {
-	let mut x = unsafe { std::mem::uninitialized() };
-	f(&mut x);
-	x
+pub fn refcall<T, U: Fn(*mut T)>(f: U) -> T {
+	let mut x = std::mem::MaybeUninit::uninit();
+	f(x.as_mut_ptr());
+	unsafe { x.assume_init() }
 }
 pub fn cstr(s: &str) -> CString {
 	CString::new(s).expect("null byte in string")
@@ -24,7 +24,7 @@ impl Shader {
 			let status = refcall(|x| gl::GetShaderiv(id, gl::COMPILE_STATUS, x));
 			if status == gl::FALSE as GLint {
 				let len = refcall(|x| gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, x));
-				let mut s = vec![0 as u8; len as usize];
+				let mut s = vec![0u8; len as usize];
 				gl::GetShaderInfoLog(id, len, std::ptr::null_mut(), s.as_mut_ptr() as *mut i8);
 				gl::DeleteShader(id);
 				return Err(String::from_utf8(s).unwrap())
@@ -53,7 +53,7 @@ impl Program {
 			let status = refcall(|x| gl::GetProgramiv(id, gl::LINK_STATUS, x));
 			if status == gl::FALSE as GLint {
 				let len = refcall(|x| gl::GetProgramiv(id, gl::INFO_LOG_LENGTH, x));
-				let mut s = vec![0 as u8; len as usize];
+				let mut s = vec![0u8; len as usize];
 				gl::GetProgramInfoLog(id, len, std::ptr::null_mut(), s.as_mut_ptr() as *mut i8);
 				gl::DeleteProgram(id);
 				return Err(String::from_utf8(s).unwrap())
@@ -6,12 +6,6 @@ use std::os::raw::*;
 use gl::types::*;
 use std::time::Instant;
 
-macro_rules! offset_of {
-	($ty:ty, $field:ident) => {
-		&(*(0 as *const $ty)).$field as *const _ as usize
-	}
-}
-
 const VERTEX_SHADER: &str = r#"
 	uniform mat4 ProjMtx;
 	attribute vec2 Position;
@@ -37,18 +31,53 @@ const FRAGMENT_SHADER: &str = r#"
 "#;
 
 pub enum DrawCmd {
+	Arc(Vec2, f32, f32, f32, Color, f32),
 	Circle(Vec2, f32, Color, f32),
 	CircleFilled(Vec2, f32, Color),
 	Line(Vec2, Vec2, Color, f32),
 	Rect(Vec2, Vec2, Color, f32),
 	RectFilled(Vec2, Vec2, Color),
+	TriangleFilled(Vec2, Vec2, Vec2, Color),
+	Text(Vec2, Color, String),
+}
+
+pub type Measure<'a> = dyn Fn(&str) -> Vec2 + 'a;
+
+unsafe fn fonttex(scale: f32) -> Texture {
+	let io = igGetIO();
+	let atlas = (*io).fonts;
+	ImFontAtlas_Clear(atlas);
+	let mut cfg : ImFontConfig = std::mem::zeroed();
+	ImFontConfig_DefaultConstructor(&mut cfg);
+	cfg.size_pixels = 13.0 * scale;
+	ImFontAtlas_AddFontDefault(atlas, &cfg);
+	(*io).font_global_scale = 1.0 / scale;
+
+	let mut pixels : *mut c_uchar = std::ptr::null_mut();
+	let mut width : c_int = 0;
+	let mut height : c_int = 0;
+	let mut bpp : c_int = 0;
+	ImFontAtlas_GetTexDataAsRGBA32(atlas, &mut pixels, &mut width, &mut height, &mut bpp);
+
+	let fonts = Texture::new();
+	fonts.bind();
+	gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+	gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+	gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+	gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, width, height, 0, gl::RGBA, gl::UNSIGNED_BYTE, pixels as *const c_void);
+	(*atlas).tex_id = fonts.id() as *mut c_void;
+	fonts
 }
 
 pub struct Imgui {
 	_fonts: Texture,
+	scale: f32,
 	lastframe: Instant,
 	w: f32,
 	h: f32,
+	fbw: f32,
+	fbh: f32,
+	wheel_h: f32,
 	vao: VAO,
 	vert: VBO,
 	elem: VBO,
@@ -88,24 +117,11 @@ impl Imgui {
 			gl::Disable(gl::DEPTH_TEST);
 			gl::Enable(gl::SCISSOR_TEST);
 
-			let mut pixels : *mut c_uchar = std::ptr::null_mut();
-			let mut width : c_int = 0;
-			let mut height : c_int = 0;
-			let mut bpp : c_int = 0;
-			ImFontAtlas_GetTexDataAsRGBA32((*io).fonts, &mut pixels, &mut width, &mut height, &mut bpp);
-
-			let fonts = Texture::new();
-
-			fonts.bind();
-			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
-			gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-			gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
-			gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA as i32, width, height, 0, gl::RGBA, gl::UNSIGNED_BYTE, pixels as *const c_void);
-			(*(*io).fonts).tex_id = fonts.id() as *mut c_void;
+			let fonts = fonttex(1.0);
 			
 			let vshad = Shader::new(VERTEX_SHADER, gl::VERTEX_SHADER).unwrap();
 			let fshad = Shader::new(FRAGMENT_SHADER, gl::FRAGMENT_SHADER).unwrap();
-			let prog = Program::new(&vec![vshad, fshad]).unwrap();
+			let prog = Program::new(&[vshad, fshad]).unwrap();
 			
 			let loctex = prog.get_uniform_location("Texture").unwrap();
 			let locprojmtx = prog.get_uniform_location("ProjMtx").unwrap();
@@ -124,14 +140,18 @@ impl Imgui {
 			gl::EnableVertexAttribArray(locpos);
 			gl::EnableVertexAttribArray(locuv);
 			gl::EnableVertexAttribArray(loccol);
-			gl::VertexAttribPointer(locpos, 2, gl::FLOAT, gl::FALSE, std::mem::size_of::<ImDrawVert>() as i32, offset_of!(ImDrawVert, pos) as *const c_void);
-			gl::VertexAttribPointer(locuv, 2, gl::FLOAT, gl::FALSE, std::mem::size_of::<ImDrawVert>() as i32, offset_of!(ImDrawVert, uv) as *const c_void);
-			gl::VertexAttribPointer(loccol, 4, gl::UNSIGNED_BYTE, gl::TRUE, std::mem::size_of::<ImDrawVert>() as i32, offset_of!(ImDrawVert, col) as *const c_void);
+			gl::VertexAttribPointer(locpos, 2, gl::FLOAT, gl::FALSE, std::mem::size_of::<ImDrawVert>() as i32, std::mem::offset_of!(ImDrawVert, pos) as *const c_void);
+			gl::VertexAttribPointer(locuv, 2, gl::FLOAT, gl::FALSE, std::mem::size_of::<ImDrawVert>() as i32, std::mem::offset_of!(ImDrawVert, uv) as *const c_void);
+			gl::VertexAttribPointer(loccol, 4, gl::UNSIGNED_BYTE, gl::TRUE, std::mem::size_of::<ImDrawVert>() as i32, std::mem::offset_of!(ImDrawVert, col) as *const c_void);
 
 			Imgui {
 				_fonts: fonts,
+				scale: 1.0,
 				lastframe: Instant::now(),
 				w, h,
+				fbw: w,
+				fbh: h,
+				wheel_h: 0.0,
 				vao,
 				vert,
 				elem: VBO::new(),
@@ -150,19 +170,36 @@ impl Imgui {
 
 			(*io).display_size.x = self.w;
 			(*io).display_size.y = self.h;
+			(*io).display_framebuffer_scale.x = self.fbw / self.w;
+			(*io).display_framebuffer_scale.y = self.fbh / self.h;
 			
 			igNewFrame();
 		}
 	}
+	pub fn resize(&mut self, w: f32, h: f32, fbw: f32, fbh: f32) {
+		self.w = w;
+		self.h = h;
+		self.fbw = fbw;
+		self.fbh = fbh;
+		let scale = (fbw / w).max(fbh / h).max(1.0);
+		if scale != self.scale {
+			self._fonts = unsafe { fonttex(scale) };
+			self.scale = scale;
+		}
+	}
+	pub fn display_size(&self) -> Vec2 {
+		Vec2::new(self.w, self.h)
+	}
 	pub fn render(&self) {
 		unsafe {
 			igEndFrame();
 			igRender();
 			
 			let io = igGetIO();
+			let (sx, sy) = (self.fbw / self.w, self.fbh / self.h);
 			
-			gl::Viewport(0, 0, self.w as i32, self.h as i32);
-			gl::Scissor(0, 0, self.w as i32, self.h as i32);
+			gl::Viewport(0, 0, self.fbw as i32, self.fbh as i32);
+			gl::Scissor(0, 0, self.fbw as i32, self.fbh as i32);
 			gl::ClearColor(0.5, 0.3, 0.4, 1.0);
 			gl::Clear(gl::COLOR_BUFFER_BIT);
 			
@@ -206,10 +243,10 @@ impl Imgui {
 						if r.x < self.w && r.y < self.h && r.z >= 0.0 && r.w >= 0.0 {
 							gl::BindTexture(gl::TEXTURE_2D, c.texture_id as u32);
 							gl::Scissor(
-								c.clip_rect.x as i32,
-								(self.h - c.clip_rect.w) as i32,
-								(c.clip_rect.z - c.clip_rect.x) as i32,
-								(c.clip_rect.w - c.clip_rect.y) as i32);
+								(r.x * sx) as i32,
+								(self.fbh - r.w * sy) as i32,
+								((r.z - r.x) * sx) as i32,
+								((r.w - r.y) * sy) as i32);
 							gl::DrawElements(gl::TRIANGLES,
 								c.elem_count as i32, 
 								gl::UNSIGNED_SHORT,
@@ -221,14 +258,15 @@ impl Imgui {
 			}
 		}
 	}
-	pub fn mouse(&self, x: i32, y: i32, l: bool, r: bool) {
+	pub fn mouse(&mut self, x: i32, y: i32, buttons: [bool; 5], wheel: Vec2) {
 		unsafe {
 			let io = igGetIO();
 			(*io).mouse_pos.x = x as f32;
 			(*io).mouse_pos.y = y as f32;
-			(*io).mouse_down[0] = l;
-			(*io).mouse_down[1] = r;
+			(*io).mouse_down = buttons;
+			(*io).mouse_wheel = wheel.y;
 		}
+		self.wheel_h = wheel.x;
 	}
 	pub fn keyboard(&self, kb: sdl2::keyboard::KeyboardState, keymod: sdl2::keyboard::Mod) {
 		unsafe {
@@ -245,7 +283,6 @@ impl Imgui {
 	pub fn add_text(&self, text: &str) {
 		unsafe {
 			let ctext = cstr(text);
-			let io = igGetIO();
 			ImGuiIO_AddInputCharactersUTF8(ctext.as_ptr());
 		}
 	}
@@ -275,6 +312,24 @@ impl Imgui {
 		unsafe { igRadioButtonBool(cs.as_ptr(), state) }
 	}
 
+	pub fn checkbox(&self, s: &str, v: &mut bool) -> bool {
+		let cs = cstr(s);
+		unsafe { igCheckbox(cs.as_ptr(), v) }
+	}
+
+	pub fn selectable(&self, s: &str, selected: bool) -> bool {
+		let cs = cstr(s);
+		unsafe { igSelectable(cs.as_ptr(), selected, ImGuiSelectableFlags::empty(), ImVec2::new(0.0, 0.0)) }
+	}
+
+	pub fn push_id(&self, i: usize) {
+		unsafe { igPushIDInt(i as i32) }
+	}
+
+	pub fn pop_id(&self) {
+		unsafe { igPopID() }
+	}
+
 	pub fn input_text(&self, s: &str, b: &mut ImguiBuf) -> bool {
 		unsafe {
 			let cs = cstr(s);
@@ -282,10 +337,38 @@ impl Imgui {
 		}
 	}
 	
+	pub fn input_text_enter(&self, s: &str, b: &mut ImguiBuf) -> bool {
+		unsafe {
+			let cs = cstr(s);
+			igInputText(cs.as_ptr(), b.as_ptr(), b.len(), ImGuiInputTextFlags::EnterReturnsTrue | ImGuiInputTextFlags::AutoSelectAll, None, std::ptr::null_mut())
+		}
+	}
+	
+	pub fn set_cursor_screen_pos(&self, p: Vec2) {
+		unsafe { igSetCursorScreenPos(p.imvec()); }
+	}
+	
+	pub fn push_item_width(&self, w: f32) {
+		unsafe { igPushItemWidth(w); }
+	}
+	
+	pub fn pop_item_width(&self) {
+		unsafe { igPopItemWidth(); }
+	}
+	
+	pub fn set_keyboard_focus_here(&self) {
+		unsafe { igSetKeyboardFocusHere(0); }
+	}
+	
 	pub fn draw(&self, l: &[DrawCmd], p: Vec2) {
 		let drawlist = unsafe { igGetWindowDrawList() };
 		for i in l {
 			match i {
+			DrawCmd::Arc(c, r, a0, a1, col, thick) =>
+				unsafe {
+					ImDrawList_PathArcTo(drawlist, (*c+p).imvec(), *r, *a0, *a1, 32);
+					ImDrawList_PathStroke(drawlist, col.to_u32(), false, *thick);
+				},
 			DrawCmd::Circle(c, r, col, thick) =>
 				unsafe { ImDrawList_AddCircle(drawlist, (*c+p).imvec(), *r, col.to_u32(), 32, *thick) },
 			DrawCmd::CircleFilled(c, r, col) =>
@@ -296,14 +379,46 @@ impl Imgui {
 				unsafe { ImDrawList_AddRect(drawlist, (*a+p).imvec(), (*b+p).imvec(), col.to_u32(), 0.0, ImDrawCornerFlags::empty(), *thick) },
 			DrawCmd::RectFilled(a, b, col) =>
 				unsafe { ImDrawList_AddRectFilled(drawlist, (*a+p).imvec(), (*b+p).imvec(), col.to_u32(), 0.0, ImDrawCornerFlags::empty()) },
+			DrawCmd::TriangleFilled(a, b, c, col) =>
+				unsafe { ImDrawList_AddTriangleFilled(drawlist, (*a+p).imvec(), (*b+p).imvec(), (*c+p).imvec(), col.to_u32()) },
+			DrawCmd::Text(a, col, s) =>
+				unsafe {
+					let b = s.as_ptr() as *const c_char;
+					ImDrawList_AddText(drawlist, (*a+p).imvec(), col.to_u32(), b, b.add(s.len()));
+				},
 			}
 		}
 	}
 	
+	pub fn text_size(&self, s: &str) -> Vec2 {
+		unsafe {
+			let b = s.as_ptr() as *const c_char;
+			let mut r = ImVec2::zero();
+			igCalcTextSize(&mut r, b, b.add(s.len()), false, -1.0);
+			Vec2::new(r.x, r.y)
+		}
+	}
+
+	pub fn push_clip_rect(&self, a: Vec2, b: Vec2) {
+		unsafe { ImDrawList_PushClipRect(igGetWindowDrawList(), a.imvec(), b.imvec(), true) }
+	}
+
+	pub fn pop_clip_rect(&self) {
+		unsafe { ImDrawList_PopClipRect(igGetWindowDrawList()) }
+	}
+
 	pub fn is_item_hovered(&self) -> bool {
 		unsafe { igIsItemHovered(ImGuiHoveredFlags::empty()) }
 	}
 
+	pub fn is_item_active(&self) -> bool {
+		unsafe { igIsItemActive() }
+	}
+
+	pub fn is_mouse_double_clicked(&self, x: i32) -> bool {
+		unsafe { igIsMouseDoubleClicked(x) }
+	}
+
 	pub fn is_mouse_clicked(&self, x: i32) -> bool {
 		unsafe { igIsMouseClicked(x, false) }
 	}
@@ -316,13 +431,21 @@ impl Imgui {
 		unsafe { igIsMouseDown(x) }
 	}
 	
-	pub fn is_key_down(&self, kc: Scancode) -> bool {
+	pub fn is_key_pressed(&self, kc: Scancode) -> bool {
+		unsafe { (kc as usize) < 512 && igIsKeyPressed(kc as i32, false) }
+	}
+	
+	pub fn wants_text_input(&self) -> bool {
 		unsafe {
 			let io = igGetIO();
-			(kc as usize) < 512 && (*io).keys_down[kc as usize]
+			(*io).want_text_input
 		}
 	}
 	
+	pub fn same_line(&self) {
+		unsafe { igSameLine(0.0, -1.0); }
+	}
+	
 	pub fn is_ctrl_down(&self) -> bool {
 		unsafe {
 			let io = igGetIO();
@@ -330,6 +453,14 @@ impl Imgui {
 		}
 	}
 	
+	pub fn mouse_wheel(&self) -> f32 {
+		unsafe { (*igGetIO()).mouse_wheel }
+	}
+
+	pub fn mouse_wheel_h(&self) -> f32 {
+		self.wheel_h
+	}
+
 	pub fn mouse_pos(&self) -> Vec2 {
 		unsafe {
 			let io = igGetIO();
@@ -338,6 +469,14 @@ impl Imgui {
 		}
 	}
 	
+	pub fn content_region_avail(&self) -> Vec2 {
+		unsafe {
+			let mut r = ImVec2::zero();
+			igGetContentRegionAvail(&mut r);
+			Vec2::new(r.x, r.y)
+		}
+	}
+
 	pub fn cursor_screen_pos(&self) -> Vec2 {
 		let p = unsafe { refcall(|x| igGetCursorScreenPos(x)) };
 		Vec2::new(p.x, p.y)
@@ -347,6 +486,7 @@ impl Imgui {
 pub struct ImguiBegin<'a> {
 	title: &'a str,
 	flags: ImGuiWindowFlags,
+	cond: ImGuiCond,
 }
 
 impl Imgui {
@@ -354,16 +494,21 @@ impl Imgui {
 		ImguiBegin {
 			title: s,
 			flags: ImGuiWindowFlags::empty(),
+			cond: ImGuiCond::Once,
 		}
 	}
 }
 impl<'a> ImguiBegin<'a> {
-	pub fn size(&'a mut self, w: f32, h: f32) -> &'a mut ImguiBegin {
-		unsafe { igSetNextWindowSize(ImVec2::new(w, h), ImGuiCond::Once); }
+	pub fn layout(&'a mut self, force: bool) -> &'a mut ImguiBegin<'a> {
+		self.cond = if force { ImGuiCond::Always } else { ImGuiCond::FirstUseEver };
 		self
 	}
-	pub fn pos(&'a mut self, w: f32, h: f32) -> &'a mut ImguiBegin {
-		unsafe { igSetNextWindowPos(ImVec2::new(w, h), ImGuiCond::Once, ImVec2::zero()); }
+	pub fn size(&'a mut self, w: f32, h: f32) -> &'a mut ImguiBegin<'a> {
+		unsafe { igSetNextWindowSize(ImVec2::new(w, h), self.cond); }
+		self
+	}
+	pub fn pos(&'a mut self, w: f32, h: f32) -> &'a mut ImguiBegin<'a> {
+		unsafe { igSetNextWindowPos(ImVec2::new(w, h), self.cond, ImVec2::zero()); }
 		self
 	}
 	pub fn begin(&self) {
@@ -382,6 +527,13 @@ impl ImguiBuf {
 	pub fn len(&self) -> usize {
 		self.v.len()
 	}
+	pub fn set(&mut self, s: &str) {
+		let n = s.len().min(self.v.len() - 1);
+		self.v[..n].copy_from_slice(&s.as_bytes()[..n]);
+		for x in &mut self.v[n..] {
+			*x = 0;
+		}
+	}
 	pub fn as_str(&self) -> String {
 		let opt_len = self.v.iter()
 			.enumerate()
@@ -1,3 +1,5 @@
+#![allow(clippy::upper_case_acronyms)]
+
 extern crate imgui_sys;
 extern crate gl;
 extern crate sdl2;
@@ -7,149 +9,1132 @@ mod dat;
 mod hjgl;
 mod hjimgui;
 mod constr;
+mod mat;
+mod ent;
+mod draw;
+mod expr;
+mod file;
+mod dxf;
+mod svg;
+mod history;
+mod view;
 
 use gfx::*;
 use dat::*;
 use hjimgui::*;
 use constr::*;
+use ent::*;
+use draw::*;
+use expr::*;
+use history::*;
+use view::*;
 
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
+const PAD : f32 = 10.0;
+const PANEL_W : f32 = 280.0;
 const POINT_RADIUS : f32 = 5.0;
+const MAX_MAJOR : u32 = 100;
+
+fn pointgrab(view: &View, l: &Points, p: Vec2) -> Vec<ID> {
+	l.iter().filter(|(_,&x)| view.screen(x).dist(p) <= POINT_RADIUS).map(|(id,_)| id).collect()
+}
 
-type Points = IDMap<Vec2>; 
+fn linegrab(view: &View, l: &Lines, pts: &Points, p: Vec2) -> Vec<ID> {
+	l.iter().filter(|(_,x)| p.dist_seg(view.screen(pts[x.a]), view.screen(pts[x.b])) <= POINT_RADIUS).map(|(id,_)| id).collect()
+}
 
-fn pointgrab(l: &Points, p: Vec2) -> Vec<ID> {
-	l.iter().filter(|(_,x)| x.dist(p) <= POINT_RADIUS).map(|(id,_)| id).collect()
+fn circlegrab(view: &View, l: &Circles, pts: &Points, p: Vec2) -> Vec<ID> {
+	l.iter().filter(|(_,x)| (p.dist(view.screen(pts[x.c])) - x.r * view.zoom).abs() <= POINT_RADIUS).map(|(id,_)| id).collect()
 }
 
-fn pointdraw(l: &Points, sel: &HashSet<ID>) -> Vec<DrawCmd> {
+fn arcgrab(view: &View, l: &Arcs, pts: &Points, p: Vec2) -> Vec<ID> {
+	l.iter().filter(|(_,x)| {
+		let c = view.screen(pts[x.c]);
+		(p.dist(c) - x.radius(pts) * view.zoom).abs() <= POINT_RADIUS && x.contains_angle(pts, (p - c).angle())
+	}).map(|(id,_)| id).collect()
+}
+
+fn foreign(path: &str) -> bool {
+	match Path::new(path).extension().and_then(|e| e.to_str()) {
+	Some(e) => e.eq_ignore_ascii_case("svg") || e.eq_ignore_ascii_case("dxf"),
+	None => false,
+	}
+}
+
+fn pointcolor(status: Status, sel: bool) -> Color {
+	match (status, sel) {
+	(Status::Under, false) => Color::new(127, 0, 0, 255),
+	(Status::Under, true) => Color::new(255, 127, 127, 255),
+	(Status::Full, false) => Color::new(0, 127, 0, 255),
+	(Status::Full, true) => Color::new(127, 255, 127, 255),
+	(Status::Redundant, false) => Color::new(160, 110, 0, 255),
+	(Status::Redundant, true) => Color::new(255, 200, 100, 255),
+	(Status::Over, false) => Color::new(127, 0, 127, 255),
+	(Status::Over, true) => Color::new(255, 127, 255, 255),
+	}
+}
+
+fn pointdraw(view: &View, l: &Points, sel: &HashSet<ID>, dof: &Dof) -> Vec<DrawCmd> {
 	l.iter().map(|(id,&c)|
-		DrawCmd::CircleFilled(c, POINT_RADIUS,
-			if sel.contains(&id) {
-				Color::new(255, 127, 127, 255)
-			} else {
-				Color::new(127, 0, 0, 255)
-			}
-		)
+		DrawCmd::CircleFilled(view.screen(c), POINT_RADIUS, pointcolor(dof.status(id), sel.contains(&id)))
+	).collect()
+}
+
+fn entstatus(dof: &Dof, ids: &[ID]) -> Status {
+	let l : Vec<Status> = ids.iter().map(|&id| dof.status(id)).collect();
+	if l.contains(&Status::Over) {
+		Status::Over
+	} else if l.contains(&Status::Redundant) {
+		Status::Redundant
+	} else if l.iter().all(|&s| s == Status::Full) {
+		Status::Full
+	} else {
+		Status::Under
+	}
+}
+
+fn linedraw(view: &View, l: &Lines, pts: &Points, cons: &HashSet<ID>, sel: &HashSet<ID>, dof: &Dof) -> Vec<DrawCmd> {
+	l.iter().map(|(id,x)| {
+		let (p, q) = (view.screen(pts[x.a]), view.screen(pts[x.b]));
+		let col = pointcolor(entstatus(dof, &[x.a, x.b]), sel.contains(&id));
+		if cons.contains(&id) {
+			let (r, g, b, _) = col.rgba();
+			DrawCmd::Line(p, q, Color::new(r, g, b, 127), 1.0)
+		} else {
+			DrawCmd::Line(p, q, col, 2.0)
+		}
+	}).collect()
+}
+
+// neutral stroke for exported geometry, whatever its constraint status
+fn ink(d: DrawCmd) -> DrawCmd {
+	let col = Color::new(0, 0, 0, 255);
+	match d {
+	DrawCmd::Line(a, b, _, w) => DrawCmd::Line(a, b, col, w),
+	DrawCmd::Circle(c, r, _, w) => DrawCmd::Circle(c, r, col, w),
+	DrawCmd::Arc(c, r, a0, a1, _, w) => DrawCmd::Arc(c, r, a0, a1, col, w),
+	DrawCmd::CircleFilled(c, r, _) => DrawCmd::CircleFilled(c, r, col),
+	d => d,
+	}
+}
+
+fn circledraw(view: &View, l: &Circles, pts: &Points, sel: &HashSet<ID>, dof: &Dof) -> Vec<DrawCmd> {
+	l.iter().map(|(id,x)|
+		DrawCmd::Circle(view.screen(pts[x.c]), x.r * view.zoom, pointcolor(entstatus(dof, &[x.c, id]), sel.contains(&id)), 2.0)
 	).collect()
 }
 
+fn arcdraw(view: &View, l: &Arcs, pts: &Points, sel: &HashSet<ID>, dof: &Dof) -> Vec<DrawCmd> {
+	l.iter().map(|(id,x)| {
+		let (a0, a1) = x.angles(pts);
+		DrawCmd::Arc(view.screen(pts[x.c]), x.radius(pts) * view.zoom, a0, a1, pointcolor(entstatus(dof, &[x.c, x.a, x.b]), sel.contains(&id)), 2.0)
+	}).collect()
+}
+
 #[derive(Debug,PartialEq)]
 enum Tool {
 	Move,
-	Add
+	Add,
+	Line,
+	Circle,
+	Arc,
+}
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+enum Action {
+	Hor,
+	Ver,
+	Coincident,
+	On,
+	Parallel,
+	Perp,
+	Tangent,
+	Equal,
+	Midpoint,
+	Symmetric,
+	Concentric,
+	Fix,
+}
+
+const ACTIONS : &[(&str, Scancode, Action)] = &[
+	("Horizontal (H)", Scancode::H, Action::Hor),
+	("Vertical (V)", Scancode::V, Action::Ver),
+	("Coincident (C)", Scancode::C, Action::Coincident),
+	("On (O)", Scancode::O, Action::On),
+	("Parallel (P)", Scancode::P, Action::Parallel),
+	("Perpendicular (L)", Scancode::L, Action::Perp),
+	("Tangent (T)", Scancode::T, Action::Tangent),
+	("Equal (E)", Scancode::E, Action::Equal),
+	("Midpoint (M)", Scancode::M, Action::Midpoint),
+	("Symmetric (S)", Scancode::S, Action::Symmetric),
+	("Concentric (N)", Scancode::N, Action::Concentric),
+	("Fix (F)", Scancode::F, Action::Fix),
+];
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+enum Dim {
+	Dist,
+	HDist,
+	VDist,
+	Angle,
+	Radius,
+	Diameter,
 }
 
+const DIMS : &[(&str, Dim)] = &[
+	("Dimension", Dim::Dist),
+	("Horiz. Dist", Dim::HDist),
+	("Vert. Dist", Dim::VDist),
+	("Angle", Dim::Angle),
+	("Radius", Dim::Radius),
+	("Diameter", Dim::Diameter),
+];
+
 struct FED {
-	points: Points,
+	sk: Sketch,
 	constrs: Constrs,
+	dof: Dof,
 	t: Tool,
 	sel: HashSet<ID>,
 	startpos: Vec2,
 	downpos: Vec2,
+	drag: Vec<(ID, Vec2)>,
+	clicks: Vec<ID>,
 	dim_buf: ImguiBuf,
-	rectsel: bool
+	edit: Option<usize>,
+	edit_buf: ImguiBuf,
+	editfocus: bool,
+	rectsel: bool,
+	rejected: Vec<(String, Vec<usize>)>,
+	driven: bool,
+	params: Params,
+	env: HashMap<String, f32>,
+	errors: Vec<String>,
+	param_buf: ImguiBuf,
+	path_buf: ImguiBuf,
+	import_buf: ImguiBuf,
+	msg: String,
+	svg_glyphs: bool,
+	svg_dims: bool,
+	svg_points: bool,
+	scale_buf: ImguiBuf,
+	locked: bool,
+	history: History,
+	before: Option<State>,
+	view: View,
+	panpos: Vec2,
+	canvas: Vec2,
+	display: Vec2,
+	resized: bool,
+	grid: bool,
+	snap: bool,
+	step_buf: ImguiBuf,
+	major_buf: ImguiBuf,
 }
 impl FED {
 	fn new() -> FED {
-		FED {
-			points: Points::new(),
+		let mut r = FED {
+			sk: Sketch::new(),
 			constrs: Vec::new(),
+			dof: Dof::new(),
 			t: Tool::Move,
 			sel: HashSet::new(),
 			startpos: Vec2::zero(),
 			downpos: Vec2::zero(),
+			drag: Vec::new(),
+			clicks: Vec::new(),
 			dim_buf: ImguiBuf::new(512),
+			edit: None,
+			edit_buf: ImguiBuf::new(512),
+			editfocus: false,
 			rectsel: false,
+			rejected: Vec::new(),
+			driven: false,
+			params: Params::new(),
+			env: HashMap::new(),
+			errors: Vec::new(),
+			param_buf: ImguiBuf::new(512),
+			path_buf: ImguiBuf::new(512),
+			import_buf: ImguiBuf::new(512),
+			msg: String::new(),
+			svg_glyphs: true,
+			svg_dims: true,
+			svg_points: false,
+			scale_buf: ImguiBuf::new(64),
+			locked: false,
+			history: History::new(),
+			before: None,
+			view: View::new(),
+			panpos: Vec2::zero(),
+			canvas: Vec2::new(600.0, 600.0),
+			display: Vec2::zero(),
+			resized: false,
+			grid: true,
+			snap: false,
+			step_buf: ImguiBuf::new(64),
+			major_buf: ImguiBuf::new(64),
+		};
+		r.path_buf.set("sketch.fed");
+		r.scale_buf.set("1");
+		r.step_buf.set("10");
+		r.major_buf.set("5");
+		r
+	}
+	fn solve(&mut self) {
+		solve(&mut self.sk, &self.constrs);
+		self.dof = analyze(&self.sk, &self.constrs);
+		self.measure();
+	}
+	fn measure(&mut self) {
+		for e in self.constrs.iter_mut().filter(|e| e.driven) {
+			if let Some(v) = e.c.measure(&self.sk) {
+				e.c.set_value((v * 1000.0).round() / 1000.0);
+			}
+		}
+	}
+	fn selpoints(&self) -> HashSet<ID> {
+		let mut r = HashSet::new();
+		for &id in &self.sel {
+			if self.sk.points.get(id).is_some() {
+				r.insert(id);
+			} else if let Some(l) = self.sk.lines.get(id) {
+				r.insert(l.a);
+				r.insert(l.b);
+			} else if let Some(c) = self.sk.circles.get(id) {
+				r.insert(c.c);
+			} else if let Some(a) = self.sk.arcs.get(id) {
+				r.insert(a.c);
+				r.insert(a.a);
+				r.insert(a.b);
+			}
+		}
+		r
+	}
+	fn selected<T>(&self, m: &IDMap<T>) -> Vec<ID> {
+		let mut l : Vec<ID> = self.sel.iter().cloned().filter(|&id| m.get(id).is_some()).collect();
+		l.sort_by_key(|id| id.slot());
+		l
+	}
+	fn selcurves(&self) -> Vec<ID> {
+		let mut l = self.selected(&self.sk.circles);
+		l.extend(self.selected(&self.sk.arcs));
+		l
+	}
+	fn sellines(&self) -> Vec<Line> {
+		self.selected(&self.sk.lines).iter().map(|&id| self.sk.lines[id]).collect()
+	}
+	fn selpairs(&self) -> Vec<(ID, ID)> {
+		let mut l : Vec<ID> = self.sel.iter().cloned().filter(|&id| self.sk.points.get(id).is_some()).collect();
+		l.sort_by_key(|id| id.slot());
+		let mut r = Vec::new();
+		for (i, &a) in l.iter().enumerate() {
+			for &b in &l[i+1..] {
+				r.push((a, b));
+			}
+		}
+		r
+	}
+	// selected lines as endpoint pairs, then point pairs no selected line already covers
+	fn pairs(&self) -> Vec<(ID, ID)> {
+		let mut r : Vec<(ID, ID)> = self.sellines().iter().map(|l| (l.a, l.b)).collect();
+		let n = r.len();
+		for (a, b) in self.selpairs() {
+			if !r[..n].iter().any(|&p| p == (a, b) || p == (b, a)) {
+				r.push((a, b));
+			}
+		}
+		r
+	}
+	fn action(&self, a: Action) -> Vec<Constr> {
+		let pts = self.selected(&self.sk.points);
+		let lines = self.selected(&self.sk.lines);
+		let curves = self.selcurves();
+		let mut r = Vec::new();
+		match a {
+		Action::Hor => r.extend(self.pairs().iter().map(|&(a, b)| Constr::Hor(a, b))),
+		Action::Ver => r.extend(self.pairs().iter().map(|&(a, b)| Constr::Ver(a, b))),
+		Action::Coincident => r.extend(pts.iter().skip(1).map(|&b| Constr::Coincident(pts[0], b))),
+		Action::On =>
+			for &p in &pts {
+				r.extend(lines.iter().map(|&l| Constr::PointOnLine(p, l)));
+				r.extend(curves.iter().map(|&c| Constr::PointOnCircle(p, c)));
+			},
+		Action::Parallel => r.extend(lines.iter().skip(1).map(|&l| Constr::Parallel(lines[0], l))),
+		Action::Perp => r.extend(lines.iter().skip(1).map(|&l| Constr::Perp(lines[0], l))),
+		Action::Tangent =>
+			for &l in &lines {
+				r.extend(curves.iter().map(|&c| Constr::Tangent(l, c)));
+			},
+		Action::Equal => {
+			r.extend(lines.iter().skip(1).map(|&l| Constr::EqualLength(lines[0], l)));
+			r.extend(curves.iter().skip(1).map(|&c| Constr::EqualRadius(curves[0], c)));
+		},
+		Action::Midpoint =>
+			for &p in &pts {
+				r.extend(lines.iter().map(|&l| Constr::Midpoint(p, l)));
+			},
+		Action::Symmetric =>
+			if pts.len() == 2 && lines.len() == 1 {
+				r.push(Constr::Symmetric(pts[0], pts[1], lines[0]));
+			},
+		Action::Concentric => r.extend(curves.iter().skip(1).map(|&c| Constr::Concentric(curves[0], c))),
+		Action::Fix => r.extend(pts.iter().map(|&p| Constr::Fixed(p, self.sk.points[p].x, self.sk.points[p].y))),
+		}
+		r
+	}
+	fn dimension(&self, k: Dim, d: f32) -> Vec<Constr> {
+		let lines = self.selected(&self.sk.lines);
+		let curves = self.selcurves();
+		let pairs = self.pairs();
+		let pts = &self.sk.points;
+		let mut r = Vec::new();
+		match k {
+		Dim::Dist => r.extend(pairs.iter().map(|&(a, b)| Constr::Dist(a, b, d))),
+		Dim::HDist => r.extend(pairs.iter().map(|&(a, b)|
+			if pts[b].x < pts[a].x { Constr::HDist(b, a, d) } else { Constr::HDist(a, b, d) }
+		)),
+		Dim::VDist => r.extend(pairs.iter().map(|&(a, b)|
+			if pts[b].y < pts[a].y { Constr::VDist(b, a, d) } else { Constr::VDist(a, b, d) }
+		)),
+		Dim::Angle =>
+			for &m in lines.iter().skip(1) {
+				if self.sk.dir(lines[0]).cross(self.sk.dir(m)) < 0.0 {
+					r.push(Constr::Angle(m, lines[0], d));
+				} else {
+					r.push(Constr::Angle(lines[0], m, d));
+				}
+			},
+		Dim::Radius => r.extend(curves.iter().map(|&c| Constr::Radius(c, d))),
+		Dim::Diameter => r.extend(curves.iter().map(|&c| Constr::Diameter(c, d))),
+		}
+		r
+	}
+	fn add_constr(&mut self, c: Constr) -> bool {
+		match check(&self.sk, &self.constrs, c) {
+		Check::Ok => {
+			self.constrs.push(Entry::new(c));
+			self.solve();
+			return true;
+		},
+		Check::Redundant(l) => self.rejected.push((format!("{} is redundant with", c), l)),
+		Check::Conflict(l) => self.rejected.push((format!("{} conflicts with", c), l)),
+		}
+		false
+	}
+	fn dimvalue(&mut self, s: &str) -> Option<(f32, Option<String>)> {
+		let r = parse(s).and_then(|e| {
+			let mut vs = Vec::new();
+			e.vars(&mut vs);
+			e.eval(&self.env).map(|v| (v, if vs.is_empty() { None } else { Some(s.trim().to_string()) }))
+		});
+		match r {
+		Ok(r) => Some(r),
+		Err(m) => {
+			self.rejected.push((format!("{}: {}", s, m), vec![]));
+			None
+		},
+		}
+	}
+	fn setparam(&mut self, s: &str) {
+		self.errors.clear();
+		let (n, e) = match s.find('=') {
+			Some(i) => (s[..i].trim(), s[i + 1..].trim()),
+			None => {
+				self.errors.push(format!("expected name = expression: {}", s));
+				return;
+			},
+		};
+		if !isident(n) {
+			self.errors.push(format!("bad parameter name '{}'", n));
+			return;
+		}
+		match self.params.iter().position(|p| p.0 == n) {
+		Some(i) => self.params[i].1 = e.to_string(),
+		None => self.params.push((n.to_string(), e.to_string())),
+		}
+		self.reeval();
+	}
+	fn reeval(&mut self) {
+		let (env, mut errs) = evaluate(&self.params);
+		for i in 0..self.constrs.len() {
+			let e = &self.constrs[i];
+			let v = match e.expr {
+				Some(ref s) if !e.driven => parse(s).and_then(|x| x.eval(&env)),
+				_ => continue,
+			};
+			let mut c = e.c;
+			match v {
+			Ok(v) if c.value() != Some(v) => c.set_value(v),
+			Ok(_) => continue,
+			Err(m) => {
+				errs.push(format!("{}: {}", c, m));
+				continue;
+			},
+			}
+			let mut others = self.constrs.clone();
+			others[i].active = false;
+			match check(&self.sk, &others, c) {
+			Check::Conflict(_) if e.active => errs.push(format!("{} conflicts, keeping {}", c, e.c)),
+			_ => self.constrs[i].c = c,
+			}
+		}
+		self.env = env;
+		self.errors = errs;
+		self.solve();
+	}
+	fn add_driven(&mut self, c: Constr) {
+		self.constrs.push(Entry { driven: true, ..Entry::new(c) });
+		self.measure();
+	}
+	fn setdriven(&mut self, i: usize, driven: bool) {
+		let c = self.constrs[i].c;
+		self.rejected.clear();
+		if driven {
+			self.constrs[i].driven = true;
+			self.constrs[i].expr = None;
+			self.solve();
+			return;
+		}
+		let mut others = self.constrs.clone();
+		others[i].active = false;
+		match check(&self.sk, &others, c) {
+		Check::Ok => {
+			self.constrs[i].driven = false;
+			self.solve();
+		},
+		Check::Redundant(l) => self.rejected.push((format!("{} is redundant with", c), l)),
+		Check::Conflict(l) => self.rejected.push((format!("{} conflicts with", c), l)),
+		}
+	}
+	fn setactive(&mut self, i: usize, active: bool) {
+		let c = self.constrs[i].c;
+		self.rejected.clear();
+		if !active || self.constrs[i].driven {
+			self.constrs[i].active = active;
+			self.solve();
+			return;
 		}
+		match check(&self.sk, &self.constrs, c) {
+		Check::Ok => {
+			self.constrs[i].active = true;
+			self.solve();
+		},
+		Check::Redundant(l) => self.rejected.push((format!("{} is redundant with", c), l)),
+		Check::Conflict(l) => self.rejected.push((format!("{} conflicts with", c), l)),
+		}
+	}
+	fn state(&self) -> State {
+		State { sk: self.sk.clone(), constrs: self.constrs.clone(), params: self.params.clone() }
+	}
+	fn changed(&self, s: &State) -> bool {
+		!self.sk.near(&s.sk, DRIFT) || self.constrs != s.constrs || self.params != s.params
+	}
+	fn record(&mut self, name: &str, s: &State) {
+		if self.changed(s) {
+			let e = Edit::new(s, &self.state());
+			self.history.push(name, e);
+		}
+	}
+	fn cmd<F: FnOnce(&mut FED)>(&mut self, name: &str, f: F) {
+		let s = self.state();
+		f(self);
+		self.record(name, &s);
+	}
+	fn restore(&mut self, s: State) {
+		self.sk = s.sk;
+		self.constrs = s.constrs;
+		self.params = s.params;
+		let (env, errs) = evaluate(&self.params);
+		self.env = env;
+		self.errors = errs;
+		let sk = &self.sk;
+		self.sel.retain(|&id| sk.contains(id));
+		self.clicks.retain(|&id| sk.contains(id));
+		self.drag.clear();
+		self.rejected.clear();
+		self.edit = None;
+		self.dof = analyze(&self.sk, &self.constrs);
+	}
+	fn undo(&mut self) {
+		self.endmove();
+		let mut s = self.state();
+		if self.history.undo(&mut s) {
+			self.restore(s);
+		}
+	}
+	fn redo(&mut self) {
+		self.endmove();
+		let mut s = self.state();
+		if self.history.redo(&mut s) {
+			self.restore(s);
+		}
+	}
+	fn save(&mut self) {
+		let path = self.path_buf.as_str();
+		if foreign(&path) {
+			self.msg = format!("won't save a sketch over {}", path);
+			return;
+		}
+		let view = vec![
+			("tool".to_string(), format!("{:?}", self.t)),
+			("pan".to_string(), format!("{} {}", self.view.pan.x, self.view.pan.y)),
+			("zoom".to_string(), format!("{}", self.view.zoom)),
+			("grid".to_string(), format!("{} {} {}", self.grid as u8, self.step_buf.as_str(), self.major_buf.as_str())),
+			("snap".to_string(), format!("{}", self.snap as u8)),
+		];
+		self.msg = match file::save(&path, &self.sk, &self.constrs, &self.params, &view) {
+			Ok(()) => format!("Saved {}", path),
+			Err(e) => e,
+		};
+	}
+	fn open(&mut self) {
+		let path = self.path_buf.as_str();
+		let doc = match file::load(&path) {
+			Ok(d) => d,
+			Err(e) => {
+				self.msg = e;
+				return;
+			},
+		};
+		self.sk = doc.sk;
+		self.constrs = doc.constrs;
+		self.params = doc.params;
+		self.view = View::new();
+		for (k, v) in doc.view {
+			let n : Vec<f32> = v.split_whitespace().filter_map(|s| s.parse().ok()).collect();
+			match (k.as_str(), &n[..]) {
+			("tool", _) => self.t = match v.as_str() {
+				"Add" => Tool::Add,
+				"Line" => Tool::Line,
+				"Circle" => Tool::Circle,
+				"Arc" => Tool::Arc,
+				_ => Tool::Move,
+				},
+			("pan", &[x, y]) => self.view.pan = Vec2::new(x, y),
+			("zoom", &[z]) if z > 0.0 => self.view.zoom = z,
+			("grid", &[on, s, m]) => {
+				self.grid = on != 0.0;
+				self.step_buf.set(&s.to_string());
+				self.major_buf.set(&m.to_string());
+			},
+			("snap", &[on]) => self.snap = on != 0.0,
+			_ => (),
+			}
+		}
+		self.sel.clear();
+		self.drag.clear();
+		self.clicks.clear();
+		self.rejected.clear();
+		self.edit = None;
+		self.history.clear();
+		self.reeval();
+		self.msg = format!("Opened {}", path);
+	}
+	fn exportpath(&self, ext: &str) -> Result<String, String> {
+		let path = self.path_buf.as_str();
+		if foreign(&path) {
+			return Err(format!("{} is not a sketch file", path));
+		}
+		let p = Path::new(&path).with_extension(ext);
+		if p == Path::new(&self.import_buf.as_str()) {
+			return Err(format!("won't export over the imported {}", p.display()));
+		}
+		Ok(p.to_string_lossy().into_owned())
+	}
+	fn svgexport(&mut self, imgui: &Imgui) {
+		let path = match self.exportpath("svg") {
+			Ok(p) => p,
+			Err(e) => {
+				self.msg = e;
+				return;
+			},
+		};
+		let scale = match self.scale_buf.as_str().parse::<f32>() {
+			Ok(s) if s > 0.0 => s,
+			_ => {
+				self.msg = format!("bad scale '{}'", self.scale_buf.as_str());
+				return;
+			},
+		};
+		let ts = |s: &str| imgui.text_size(s);
+		let none = HashSet::new();
+		let v = &View::new();
+		let sk = self.sk.solid();
+		let constrs : Constrs = self.constrs.iter().filter(|e| e.c.ids().iter().all(|&id| sk.contains(id))).cloned().collect();
+		let mut l = linedraw(v, &sk.lines, &sk.points, &none, &none, &self.dof);
+		l.extend(circledraw(v, &sk.circles, &sk.points, &none, &self.dof));
+		l.extend(arcdraw(v, &sk.arcs, &sk.points, &none, &self.dof));
+		let mut l : Vec<DrawCmd> = l.into_iter().map(ink).collect();
+		l.extend(constrdraw(&ts, v, &sk, &constrs, self.svg_glyphs, self.svg_dims));
+		if self.svg_points {
+			l.extend(pointdraw(v, &sk.points, &none, &self.dof).into_iter().map(ink));
+		}
+		self.msg = match svg::save(&path, &ts, &l, scale) {
+			Ok(()) => format!("Exported {}", path),
+			Err(e) => e,
+		};
+	}
+	fn svgimport(&mut self) {
+		let path = self.import_buf.as_str();
+		let polys = match svg::load(&path) {
+			Ok(l) => l,
+			Err(e) => {
+				self.msg = e;
+				return;
+			},
+		};
+		let mut n = 0;
+		for l in &polys {
+			let first = self.sk.ids.alloc();
+			self.sk.points.insert(first, l[0]);
+			if self.locked {
+				self.sk.construction.insert(first);
+			}
+			let mut prev = first;
+			for (i, &p) in l.iter().enumerate().skip(1) {
+				let id = if i == l.len() - 1 && p.dist(l[0]) < svg::EPS {
+					first
+				} else {
+					let id = self.sk.ids.alloc();
+					self.sk.points.insert(id, p);
+					if self.locked {
+						self.sk.construction.insert(id);
+					}
+					id
+				};
+				if id == prev {
+					continue;
+				}
+				let line = self.sk.ids.alloc();
+				self.sk.lines.insert(line, Line { a: prev, b: id });
+				if self.locked {
+					self.sk.construction.insert(line);
+				}
+				prev = id;
+				n += 1;
+			}
+		}
+		self.msg = format!("Imported {} segments from {}", n, path);
+		self.solve();
+	}
+	fn delete(&mut self) {
+		if self.sel.is_empty() {
+			return;
+		}
+		let gone = self.sk.delete(&self.sel);
+		self.constrs.retain(|e| e.c.ids().iter().all(|id| !gone.contains(id)));
+		self.sel.clear();
+		self.drag.clear();
+		self.clicks.retain(|id| !gone.contains(id));
+		self.rejected.clear();
+		self.edit = None;
+		self.solve();
+	}
+	fn gridstep(&self) -> Option<(f32, u32)> {
+		match (self.step_buf.as_str().parse::<f32>(), self.major_buf.as_str().parse::<u32>()) {
+		(Ok(s), Ok(m)) if s > 0.0 && m > 0 => Some((s, m.min(MAX_MAJOR))),
+		_ => None,
+		}
+	}
+	fn at(&self, p: Vec2) -> Vec2 {
+		let w = self.view.world(p);
+		match self.gridstep() {
+		Some((s, _)) if self.snap => snap(w, s),
+		_ => w,
+		}
+	}
+	fn grab(&self, p: Vec2) -> Vec<ID> {
+		let (v, sk) = (&self.view, &self.sk);
+		let mut g = pointgrab(v, &sk.points, p);
+		if g.is_empty() {
+			g = linegrab(v, &sk.lines, &sk.points, p);
+			g.extend(circlegrab(v, &sk.circles, &sk.points, p));
+			g.extend(arcgrab(v, &sk.arcs, &sk.points, p));
+		}
+		g
+	}
+	fn startedit(&mut self, i: usize) {
+		if let Some(v) = self.constrs[i].c.value() {
+			match self.constrs[i].expr {
+			Some(ref s) => self.edit_buf.set(s),
+			None => self.edit_buf.set(&format!("{}", v)),
+			}
+			self.edit = Some(i);
+			self.editfocus = true;
+		}
+	}
+	fn setdim(&mut self, i: usize, v: f32, expr: Option<String>) {
+		let mut c = self.constrs[i].c;
+		c.set_value(v);
+		let mut others = self.constrs.clone();
+		others[i].active = false;
+		self.rejected.clear();
+		match check(&self.sk, &others, c) {
+		Check::Conflict(l) if self.constrs[i].active => self.rejected.push((format!("{} conflicts with", c), l)),
+		_ => {
+			self.constrs[i].c = c;
+			self.constrs[i].expr = expr;
+			self.solve();
+		},
+		}
+	}
+	fn editdraw(&mut self, imgui: &Imgui, cp: Vec2) {
+		let i = match self.edit {
+			Some(i) => i,
+			None => return,
+		};
+		let (a, _) = match dimlabel(&|s| imgui.text_size(s), &self.view, &self.sk, &self.constrs[i].c) {
+			Some(r) => r,
+			None => return,
+		};
+		imgui.set_cursor_screen_pos(cp + a);
+		imgui.push_item_width(80.0);
+		if self.editfocus {
+			imgui.set_keyboard_focus_here();
+		}
+		let done = imgui.input_text_enter("##dim", &mut self.edit_buf);
+		imgui.pop_item_width();
+		if done {
+			let s = self.edit_buf.as_str();
+			self.rejected.clear();
+			if let Some((v, e)) = self.dimvalue(&s) {
+				self.cmd("Edit dimension", |f| f.setdim(i, v, e));
+			}
+			self.edit = None;
+		} else if !self.editfocus && !imgui.is_item_active() {
+			self.edit = None;
+		}
+		self.editfocus = false;
 	}
 	fn moveclick(&mut self, p: Vec2, ctrl: bool) {
-		let g = pointgrab(&self.points, p);
-		let sel_clicked = g.iter().fold(true, |a, x| a&&self.sel.contains(x));
+		self.endmove();
+		let g = self.grab(p);
+		let sel_clicked = g.iter().all(|x| self.sel.contains(x));
 		if !ctrl && !sel_clicked {
 			self.sel.clear();
 		}
-		if g.len() == 0 {
-			self.rectsel = true;
+		if let Some(&i) = g.first() {
+			self.sel.insert(i);
 		} else {
-			for i in g {
-				self.sel.insert(i);
-				break;
-			}
+			self.rectsel = true;
 		}
 		self.startpos = p;
 		self.downpos = p;
+		self.drag = self.selpoints().iter().map(|&i| (i, self.sk.points[i])).collect();
+		if !self.drag.is_empty() {
+			self.before = Some(self.state());
+		}
 	}
 	fn movedown(&mut self, p: Vec2) {
 		if self.rectsel {
 		} else {
-			for &i in &self.sel {
-				self.points[i] += p - self.downpos;
+			let mut d = (p - self.startpos) * (1.0 / self.view.zoom);
+			match (self.drag.first(), self.gridstep()) {
+			(Some(&(_, q)), Some((s, _))) if self.snap => d = snap(q + d, s) - q,
+			_ => (),
 			}
+			let targets : Vec<(ID, Vec2)> = self.drag.iter().map(|&(i, q)| (i, q + d)).collect();
+			solve_drag(&mut self.sk, &self.constrs, &targets);
+			self.dof = analyze(&self.sk, &self.constrs);
+			self.measure();
 		}
 		self.downpos = p;
 	}
+	fn endmove(&mut self) {
+		if let Some(s) = self.before.take() {
+			self.record("Move", &s);
+		}
+	}
 	fn moveup(&mut self, p: Vec2) {
+		self.endmove();
 		if self.rectsel {
 			let minx = if p.x < self.startpos.x { p.x } else { self.startpos.x };
 			let maxx = if p.x > self.startpos.x { p.x } else { self.startpos.x };
 			let miny = if p.y < self.startpos.y { p.y } else { self.startpos.y };
 			let maxy = if p.y > self.startpos.y { p.y } else { self.startpos.y };
-			self.sel = self.points.iter().filter(|&(_, &q)| q.x >= minx && q.x <= maxx && q.y >= miny && q.y <= maxy).map(|(id,_)| id).collect();
+			let v = self.view;
+			self.sel = self.sk.points.iter().map(|(id, &q)| (id, v.screen(q))).filter(|&(_, q)| q.x >= minx && q.x <= maxx && q.y >= miny && q.y <= maxy).map(|(id,_)| id).collect();
+			let lines : Vec<ID> = self.sk.lines.iter().filter(|(_, l)| self.sel.contains(&l.a) && self.sel.contains(&l.b)).map(|(id,_)| id).collect();
+			self.sel.extend(lines);
+			let circles : Vec<ID> = self.sk.circles.iter().filter(|(_, c)| self.sel.contains(&c.c)).map(|(id,_)| id).collect();
+			self.sel.extend(circles);
+			let arcs : Vec<ID> = self.sk.arcs.iter().filter(|(_, a)| self.sel.contains(&a.c) && self.sel.contains(&a.a) && self.sel.contains(&a.b)).map(|(id,_)| id).collect();
+			self.sel.extend(arcs);
 			self.rectsel = false;
 		}
 	}
+	fn pointat(&mut self, p: Vec2) -> ID {
+		match pointgrab(&self.view, &self.sk.points, p).first() {
+			Some(&id) => id,
+			None => {
+				let id = self.sk.ids.alloc();
+				let q = self.at(p);
+				self.sk.points.insert(id, q);
+				id
+			}
+		}
+	}
+	fn lineclick(&mut self, p: Vec2) {
+		let id = self.pointat(p);
+		if let Some(&a) = self.clicks.first() {
+			if a != id {
+				self.sk.lines.insert(self.sk.ids.alloc(), Line { a, b: id });
+			}
+		}
+		self.clicks = vec![id];
+		self.solve();
+	}
+	fn circleclick(&mut self, p: Vec2) {
+		if let Some(&c) = self.clicks.first() {
+			let r = self.at(p).dist(self.sk.points[c]);
+			if r > 0.0 {
+				self.sk.circles.insert(self.sk.ids.alloc(), Circle { c, r });
+				self.clicks.clear();
+			}
+		} else {
+			let c = self.pointat(p);
+			self.clicks.push(c);
+		}
+		self.solve();
+	}
+	fn arcclick(&mut self, p: Vec2) {
+		match self.clicks[..] {
+		[] => {
+			let c = self.pointat(p);
+			self.clicks.push(c);
+		},
+		[c] => {
+			let a = self.pointat(p);
+			if a != c {
+				self.clicks.push(a);
+			}
+		},
+		[c, a, ..] => {
+			let pc = self.sk.points[c];
+			let r = pc.dist(self.sk.points[a]);
+			let d = self.at(p) - pc;
+			let l = d.dot(d).sqrt();
+			if l > 0.0 {
+				let b = self.sk.ids.alloc();
+				self.sk.points.insert(b, pc + d * (r / l));
+				self.sk.arcs.insert(self.sk.ids.alloc(), Arc { c, a, b });
+				self.clicks.clear();
+			}
+		},
+		}
+		self.solve();
+	}
+	fn preview(&self, p: Vec2) -> Vec<DrawCmd> {
+		let col = Color::new(0, 0, 0, 127);
+		let pt = |id| self.view.screen(self.sk.points[id]);
+		match (&self.t, &self.clicks[..]) {
+		(Tool::Line, &[a]) => vec![DrawCmd::Line(pt(a), p, col, 1.0)],
+		(Tool::Circle, &[c]) => vec![DrawCmd::Circle(pt(c), p.dist(pt(c)), col, 1.0)],
+		(Tool::Arc, &[c]) => vec![DrawCmd::Line(pt(c), p, col, 1.0)],
+		(Tool::Arc, &[c, a]) => {
+			let a0 = (pt(a) - pt(c)).angle();
+			let mut a1 = (p - pt(c)).angle();
+			while a1 <= a0 {
+				a1 += 2.0 * std::f32::consts::PI;
+			}
+			vec![DrawCmd::Arc(pt(c), pt(c).dist(pt(a)), a0, a1, col, 1.0)]
+		},
+		_ => vec![],
+		}
+	}
 	fn render(&mut self, imgui: &mut Imgui) {
+		let ds = imgui.display_size();
+		self.resized = ds != self.display;
+		self.display = ds;
 		imgui.window("Derp")
-			.pos(100.0, 100.0)
+			.layout(self.resized)
+			.pos(PAD, PAD)
+			.size(ds.x - PANEL_W - 3.0 * PAD, ds.y - 2.0 * PAD)
 			.begin();
 		
+		if self.dof.is_over() {
+			imgui.text(&format!("DOF: {} (over-constrained)", self.dof.total));
+		} else if self.dof.is_redundant() {
+			imgui.text(&format!("DOF: {} (redundant)", self.dof.total));
+		} else {
+			imgui.text(&format!("DOF: {}", self.dof.total));
+		}
+		imgui.input_text("File", &mut self.path_buf);
+		if imgui.button("Save", Vec2::zero()) {
+			self.save();
+		}
+		imgui.same_line();
+		if imgui.button("Open", Vec2::zero()) {
+			self.open();
+		}
+		imgui.same_line();
+		if imgui.button("Export DXF", Vec2::zero()) {
+			self.msg = match self.exportpath("dxf").and_then(|p| dxf::save(&p, &self.sk).map(|()| p)) {
+				Ok(p) => format!("Exported {}", p),
+				Err(e) => e,
+			};
+		}
+		imgui.same_line();
+		if imgui.button("Export SVG", Vec2::zero()) {
+			self.svgexport(imgui);
+		}
+		imgui.same_line();
+		imgui.checkbox("Glyphs", &mut self.svg_glyphs);
+		imgui.same_line();
+		imgui.checkbox("Dims", &mut self.svg_dims);
+		imgui.same_line();
+		imgui.checkbox("Points", &mut self.svg_points);
+		imgui.same_line();
+		imgui.push_item_width(60.0);
+		imgui.input_text("mm/unit", &mut self.scale_buf);
+		imgui.pop_item_width();
+		imgui.input_text("Import", &mut self.import_buf);
+		if imgui.button("Import DXF", Vec2::zero()) {
+			self.cmd("Import DXF", |f| {
+				let path = f.import_buf.as_str();
+				f.msg = match dxf::load(&path, &mut f.sk) {
+					Ok((n, 0)) => format!("Imported {} entities from {}", n, path),
+					Ok((n, k)) => format!("Imported {} entities from {}, skipped {} unsupported", n, path, k),
+					Err(e) => e,
+				};
+				f.solve();
+			});
+		}
+		imgui.same_line();
+		if imgui.button("Import SVG", Vec2::zero()) {
+			self.cmd("Import SVG", |f| f.svgimport());
+		}
+		imgui.same_line();
+		imgui.checkbox("As construction", &mut self.locked);
+		if !self.msg.is_empty() {
+			imgui.same_line();
+			imgui.text(&self.msg);
+		}
+		let undo = match self.history.undo_name() {
+			Some(n) => format!("Undo {}", n),
+			None => "Undo".to_string(),
+		};
+		let pressed = imgui.button(&format!("{}###undo", undo), Vec2::zero());
+		if pressed || !imgui.wants_text_input() && imgui.is_ctrl_down() && imgui.is_key_pressed(Scancode::Z) {
+			self.undo();
+		}
+		imgui.same_line();
+		let redo = match self.history.redo_name() {
+			Some(n) => format!("Redo {}", n),
+			None => "Redo".to_string(),
+		};
+		let pressed = imgui.button(&format!("{}###redo", redo), Vec2::zero());
+		if pressed || !imgui.wants_text_input() && imgui.is_ctrl_down() && imgui.is_key_pressed(Scancode::Y) {
+			self.redo();
+		}
 		if imgui.radio_button("Move", self.t == Tool::Move) {
 			self.t = Tool::Move;
 		}
 		if imgui.radio_button("Add", self.t == Tool::Add) {
 			self.t = Tool::Add;
 		}
-		if imgui.button("Horizontal", Vec2::zero()) {
-			for &a in &self.sel {
-				for &b in &self.sel {
-					if a != b {
-						self.constrs.push(Constr::Hor(a, b));
-					}
-				}
-			}
+		if imgui.radio_button("Add Line", self.t == Tool::Line) {
+			self.t = Tool::Line;
+			self.clicks.clear();
 		}
-		if imgui.button("Vertical", Vec2::zero()) {
-			for &a in &self.sel {
-				for &b in &self.sel {
-					if a != b {
-						self.constrs.push(Constr::Ver(a, b));
-					}
-				}
+		if imgui.radio_button("Add Circle", self.t == Tool::Circle) {
+			self.t = Tool::Circle;
+			self.clicks.clear();
+		}
+		if imgui.radio_button("Add Arc", self.t == Tool::Arc) {
+			self.t = Tool::Arc;
+			self.clicks.clear();
+		}
+		for (i, &(name, key, a)) in ACTIONS.iter().enumerate() {
+			if i % 3 != 0 {
+				imgui.same_line();
 			}
+			let pressed = imgui.button(name, Vec2::zero());
+			if pressed || !imgui.wants_text_input() && !imgui.is_ctrl_down() && imgui.is_key_pressed(key) {
+				self.rejected.clear();
+				self.cmd("Add constraint", |f| for c in f.action(a) {
+					f.add_constr(c);
+				});
+			}
+		}
+		if imgui.button("Zoom to fit", Vec2::zero()) {
+			self.view.fit(&self.sk, self.canvas, 20.0);
+		}
+		imgui.same_line();
+		imgui.checkbox("Grid", &mut self.grid);
+		imgui.same_line();
+		imgui.checkbox("Snap", &mut self.snap);
+		imgui.same_line();
+		imgui.push_item_width(50.0);
+		imgui.input_text("Step", &mut self.step_buf);
+		imgui.same_line();
+		imgui.input_text("Major", &mut self.major_buf);
+		imgui.pop_item_width();
+		let pressed = imgui.button("Delete (Del)", Vec2::zero());
+		if pressed || !imgui.wants_text_input() && imgui.is_key_pressed(Scancode::Delete) {
+			self.cmd("Delete", |f| f.delete());
 		}
 		imgui.input_text("Dim", &mut self.dim_buf);
-		if imgui.button("Dimension", Vec2::zero()) {
-			if let Ok(d) = self.dim_buf.as_str().parse::<f32>() {
-				for &a in &self.sel {
-					for &b in &self.sel {
-						if a != b {
-							self.constrs.push(Constr::Dist(a, b, d));
+		imgui.checkbox("Driven", &mut self.driven);
+		for (i, &(name, k)) in DIMS.iter().enumerate() {
+			if i % 3 != 0 {
+				imgui.same_line();
+			}
+			if imgui.button(name, Vec2::zero()) {
+				self.rejected.clear();
+				self.cmd("Add dimension", |f| if f.driven {
+					for c in f.dimension(k, 0.0) {
+						f.add_driven(c);
+					}
+				} else if let Some((d, e)) = f.dimvalue(&f.dim_buf.as_str()) {
+					for c in f.dimension(k, d) {
+						if f.add_constr(c) {
+							f.constrs.last_mut().unwrap().expr = e.clone();
 						}
 					}
-				}
+				});
+			}
+		}
+		for (m, l) in &self.rejected {
+			imgui.text(m);
+			for &i in l {
+				imgui.text(&format!("    {}", self.constrs[i].c));
 			}
 		}
 		
 		let cp = imgui.cursor_screen_pos();
-		imgui.invisible_button("canvas", Vec2::new(600.0, 600.0));
-		imgui.draw(&[DrawCmd::RectFilled(cp, cp + Vec2::new(600.0, 600.0), Color::new(255, 255, 255, 255))], Vec2::zero());
+		let avail = imgui.content_region_avail();
+		self.canvas = Vec2::new(avail.x.max(100.0), avail.y.max(100.0));
+		imgui.invisible_button("canvas", self.canvas);
+		imgui.draw(&[DrawCmd::RectFilled(cp, cp + self.canvas, Color::new(255, 255, 255, 255))], Vec2::zero());
 		let p = imgui.mouse_pos() - cp;
+		if !imgui.is_mouse_down(0) {
+			self.endmove();
+		}
 		if imgui.is_item_hovered() {
-                       match self.t {
+			if imgui.is_mouse_clicked(2) {
+				self.panpos = p;
+			} else if imgui.is_mouse_down(2) {
+				self.view.pan += p - self.panpos;
+				self.panpos = p;
+			}
+			if imgui.mouse_wheel() != 0.0 {
+				self.view.zoom_at(p, 1.1f32.powf(imgui.mouse_wheel()));
+			}
+			self.view.pan.x -= imgui.mouse_wheel_h() * 20.0;
+			match self.t {
 			Tool::Move => {
-				if imgui.is_mouse_clicked(0) {
+				let dim = if imgui.is_mouse_double_clicked(0) {
+					dimgrab(&|s| imgui.text_size(s), &self.view, &self.sk, &self.constrs, p)
+				} else {
+					None
+				};
+				if let Some(i) = dim {
+					self.rectsel = false;
+					self.startedit(i);
+				} else if imgui.is_mouse_clicked(0) {
 					self.moveclick(p, imgui.is_ctrl_down());
 				} else if imgui.is_mouse_down(0) {
 					self.movedown(p);
@@ -159,14 +1144,147 @@ impl FED {
 			},
 			Tool::Add =>
 				if imgui.is_mouse_clicked(0) {
-					self.points.insert(ID::new(), p)
-				}
+					self.cmd("Add point", |f| {
+						let q = f.at(p);
+						f.sk.points.insert(f.sk.ids.alloc(), q);
+						f.solve();
+					});
+				},
+			Tool::Line =>
+				if imgui.is_mouse_clicked(0) {
+					self.cmd("Add line", |f| f.lineclick(p));
+				} else if imgui.is_mouse_clicked(1) {
+					self.clicks.clear();
+				},
+			Tool::Circle =>
+				if imgui.is_mouse_clicked(0) {
+					self.cmd("Add circle", |f| f.circleclick(p));
+				} else if imgui.is_mouse_clicked(1) {
+					self.clicks.clear();
+				},
+			Tool::Arc =>
+				if imgui.is_mouse_clicked(0) {
+					self.cmd("Add arc", |f| f.arcclick(p));
+				} else if imgui.is_mouse_clicked(1) {
+					self.clicks.clear();
+				},
 			}
 		}
-		imgui.draw(&pointdraw(&self.points, &self.sel), cp);
+		let (v, sk) = (&self.view, &self.sk);
+		imgui.push_clip_rect(cp, cp + self.canvas);
+		match self.gridstep() {
+		Some((s, m)) if self.grid => imgui.draw(&griddraw(&self.view, self.canvas, s, m), cp),
+		_ => (),
+		}
+		imgui.draw(&linedraw(v, &sk.lines, &sk.points, &sk.construction, &self.sel, &self.dof), cp);
+		imgui.draw(&circledraw(v, &sk.circles, &sk.points, &self.sel, &self.dof), cp);
+		imgui.draw(&arcdraw(v, &sk.arcs, &sk.points, &self.sel, &self.dof), cp);
+		imgui.draw(&constrdraw(&|s| imgui.text_size(s), v, sk, &self.constrs, true, true), cp);
+		imgui.draw(&self.preview(p), cp);
+		imgui.draw(&pointdraw(v, &sk.points, &self.sel, &self.dof), cp);
 		if self.rectsel {
 			imgui.draw(&[DrawCmd::Rect(self.startpos, self.downpos, Color::new(0, 0, 0, 255), 1.0)], cp);
 		}
+		imgui.pop_clip_rect();
+		self.editdraw(imgui, cp);
+		imgui.end();
+		self.constrlist(imgui);
+		self.paramlist(imgui);
+	}
+	fn paramlist(&mut self, imgui: &Imgui) {
+		let ds = imgui.display_size();
+		let h = (ds.y - 3.0 * PAD) / 2.0;
+		imgui.window("Parameters")
+			.layout(self.resized)
+			.pos(ds.x - PANEL_W - PAD, 2.0 * PAD + h)
+			.size(PANEL_W, h)
+			.begin();
+		let mut del = None;
+		for (i, (n, e)) in self.params.iter().enumerate() {
+			imgui.push_id(i);
+			if imgui.button("X", Vec2::zero()) {
+				del = Some(i);
+			}
+			imgui.same_line();
+			let s = match self.env.get(n) {
+				Some(v) => format!("{} = {} ({})", n, e, v),
+				None => format!("{} = {}", n, e),
+			};
+			if imgui.selectable(&s, false) {
+				self.param_buf.set(&format!("{} = {}", n, e));
+			}
+			imgui.pop_id();
+		}
+		if let Some(i) = del {
+			self.cmd("Delete parameter", |f| {
+				f.params.remove(i);
+				f.reeval();
+			});
+		}
+		let enter = imgui.input_text_enter("##param", &mut self.param_buf);
+		imgui.same_line();
+		if imgui.button("Set", Vec2::zero()) || enter {
+			let s = self.param_buf.as_str();
+			self.cmd("Set parameter", |f| f.setparam(&s));
+		}
+		for m in &self.errors {
+			imgui.text(m);
+		}
+		imgui.end();
+	}
+	fn constrlist(&mut self, imgui: &Imgui) {
+		let ds = imgui.display_size();
+		let h = (ds.y - 3.0 * PAD) / 2.0;
+		imgui.window("Constraints")
+			.layout(self.resized)
+			.pos(ds.x - PANEL_W - PAD, PAD)
+			.size(PANEL_W, h)
+			.begin();
+		let mut active = None;
+		let mut del = None;
+		let mut toggle = None;
+		for i in 0..self.constrs.len() {
+			imgui.push_id(i);
+			let mut on = self.constrs[i].active;
+			if imgui.checkbox("##on", &mut on) {
+				active = Some(i);
+			}
+			imgui.same_line();
+			if imgui.button("X", Vec2::zero()) {
+				del = Some(i);
+			}
+			if self.constrs[i].c.value().is_some() {
+				imgui.same_line();
+				if imgui.button("Ref", Vec2::zero()) {
+					toggle = Some(i);
+				}
+			}
+			imgui.same_line();
+			let e = &self.constrs[i];
+			let ids = e.c.ids();
+			let on = ids.len() == self.sel.len() && ids.iter().all(|x| self.sel.contains(x));
+			let s = if e.driven { format!("{} (ref)", e.c) } else { format!("{}", e.c) };
+			if imgui.selectable(&s, on) {
+				self.sel = ids.into_iter().collect();
+			}
+			imgui.pop_id();
+		}
+		if let Some(i) = active {
+			let a = !self.constrs[i].active;
+			self.cmd("Toggle constraint", |f| f.setactive(i, a));
+		}
+		if let Some(i) = toggle {
+			let d = !self.constrs[i].driven;
+			self.cmd("Toggle reference", |f| f.setdriven(i, d));
+		}
+		if let Some(i) = del {
+			self.cmd("Delete constraint", |f| {
+				f.constrs.remove(i);
+				f.rejected.clear();
+				f.edit = None;
+				f.solve();
+			});
+		}
 		imgui.end();
 	}
 }
@@ -175,6 +1293,7 @@ fn main() {
 	let mut fed = FED::new();
 	let mut gfx = GFX::new();
 	let mut imgui = Imgui::new(900.0, 900.0);
+	gfx.resize(&mut imgui);
 	
 	while gfx.frame_start(&mut imgui) {
 		fed.render(&mut imgui);
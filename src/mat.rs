@@ -0,0 +1,144 @@
+use std::ops::*;
+
+#[derive(Clone,Debug)]
+pub struct Mat {
+	rows: usize,
+	cols: usize,
+	data: Vec<f64>,
+}
+impl Mat {
+	pub fn new(rows: usize, cols: usize) -> Mat {
+		Mat { rows, cols, data: vec![0.0; rows * cols] }
+	}
+	pub fn rows(&self) -> usize {
+		self.rows
+	}
+	pub fn row(&self, i: usize) -> &[f64] {
+		&self.data[i * self.cols..(i + 1) * self.cols]
+	}
+	pub fn ata(&self) -> Mat {
+		let mut m = Mat::new(self.cols, self.cols);
+		for i in 0..self.cols {
+			for j in i..self.cols {
+				let s = (0..self.rows).map(|k| self[(k,i)] * self[(k,j)]).sum();
+				m[(i,j)] = s;
+				m[(j,i)] = s;
+			}
+		}
+		m
+	}
+	pub fn atv(&self, v: &[f64]) -> Vec<f64> {
+		(0..self.cols).map(|i| (0..self.rows).map(|k| self[(k,i)] * v[k]).sum()).collect()
+	}
+	pub fn row_basis(&self, eps: f64) -> (Basis, Vec<bool>) {
+		let mut basis = Basis::new();
+		let dep = (0..self.rows).map(|i| !basis.add(self.row(i), eps)).collect();
+		(basis, dep)
+	}
+}
+impl Index<(usize,usize)> for Mat {
+	type Output = f64;
+	fn index(&self, (i, j): (usize, usize)) -> &f64 {
+		&self.data[i * self.cols + j]
+	}
+}
+impl IndexMut<(usize,usize)> for Mat {
+	fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut f64 {
+		&mut self.data[i * self.cols + j]
+	}
+}
+
+pub struct Basis {
+	q: Vec<Vec<f64>>,
+	combo: Vec<Vec<f64>>,
+	n: usize,
+}
+impl Basis {
+	pub fn new() -> Basis {
+		Basis { q: Vec::new(), combo: Vec::new(), n: 0 }
+	}
+	pub fn rank(&self) -> usize {
+		self.q.len()
+	}
+	pub fn vectors(&self) -> &[Vec<f64>] {
+		&self.q
+	}
+	fn reduce(&self, v: &[f64]) -> (Vec<f64>, Vec<f64>) {
+		let mut r = v.to_vec();
+		let mut c = vec![0.0; self.n];
+		for _ in 0..2 {
+			for (q, qc) in self.q.iter().zip(&self.combo) {
+				let d = dot(q, &r);
+				for (a, b) in r.iter_mut().zip(q) {
+					*a -= d * b;
+				}
+				for (a, b) in c.iter_mut().zip(qc) {
+					*a += d * b;
+				}
+			}
+		}
+		(r, c)
+	}
+	pub fn add(&mut self, v: &[f64], eps: f64) -> bool {
+		let (r, c) = self.reduce(v);
+		self.n += 1;
+		let n = dot(&r, &r).sqrt();
+		if n <= eps * dot(v, v).sqrt().max(1.0) {
+			return false;
+		}
+		let mut qc : Vec<f64> = c.iter().map(|a| -a / n).collect();
+		qc.push(1.0 / n);
+		self.q.push(r.iter().map(|a| a / n).collect());
+		self.combo.push(qc);
+		true
+	}
+	pub fn combination(&self, v: &[f64], eps: f64) -> Option<Vec<f64>> {
+		let (r, c) = self.reduce(v);
+		if dot(&r, &r).sqrt() <= eps * dot(v, v).sqrt().max(1.0) {
+			Some(c)
+		} else {
+			None
+		}
+	}
+}
+
+pub fn dot(a: &[f64], b: &[f64]) -> f64 {
+	a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+pub fn cholesky_solve(a: &Mat, b: &[f64]) -> Option<Vec<f64>> {
+	let n = a.rows();
+	let mut l = Mat::new(n, n);
+	for j in 0..n {
+		let mut d = a[(j,j)];
+		for k in 0..j {
+			d -= l[(j,k)] * l[(j,k)];
+		}
+		if d <= 0.0 {
+			return None;
+		}
+		let d = d.sqrt();
+		l[(j,j)] = d;
+		for i in j+1..n {
+			let mut s = a[(i,j)];
+			for k in 0..j {
+				s -= l[(i,k)] * l[(j,k)];
+			}
+			l[(i,j)] = s / d;
+		}
+	}
+	let mut y = b.to_vec();
+	for i in 0..n {
+		for k in 0..i {
+			y[i] -= l[(i,k)] * y[k];
+		}
+		y[i] /= l[(i,i)];
+	}
+	for i in (0..n).rev() {
+		for k in i+1..n {
+			y[i] -= l[(k,i)] * y[k];
+		}
+		y[i] /= l[(i,i)];
+	}
+	Some(y)
+}
@@ -0,0 +1,466 @@
+use dat::*;
+use hjimgui::*;
+
+use std::f32::consts::PI;
+use std::fs;
+use std::fmt::Write;
+
+const MARGIN : f32 = 10.0;
+
+fn paint(col: Color) -> String {
+	let (r, g, b, a) = col.rgba();
+	if a == 255 {
+		format!("rgb({},{},{})", r, g, b)
+	} else {
+		format!("rgba({},{},{},{})", r, g, b, a as f32 / 255.0)
+	}
+}
+
+fn escape(s: &str) -> String {
+	s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn bounds(ts: &Measure, l: &[DrawCmd]) -> (Vec2, Vec2) {
+	let mut pts : Vec<Vec2> = Vec::new();
+	for d in l {
+		match *d {
+		DrawCmd::Arc(c, r, ..) | DrawCmd::Circle(c, r, ..) | DrawCmd::CircleFilled(c, r, _) =>
+			pts.extend(&[c - Vec2::new(r, r), c + Vec2::new(r, r)]),
+		DrawCmd::Line(a, b, ..) | DrawCmd::Rect(a, b, ..) | DrawCmd::RectFilled(a, b, _) => pts.extend(&[a, b]),
+		DrawCmd::TriangleFilled(a, b, c, _) => pts.extend(&[a, b, c]),
+		DrawCmd::Text(p, _, ref s) => pts.extend(&[p, p + ts(s)]),
+		}
+	}
+	if pts.is_empty() {
+		return (Vec2::zero(), Vec2::zero());
+	}
+	let mut lo = pts[0];
+	let mut hi = pts[0];
+	for p in pts {
+		lo = Vec2::new(lo.x.min(p.x), lo.y.min(p.y));
+		hi = Vec2::new(hi.x.max(p.x), hi.y.max(p.y));
+	}
+	(lo, hi)
+}
+
+fn elem(ts: &Measure, s: &mut String, d: &DrawCmd) {
+	match *d {
+	DrawCmd::Arc(c, r, a0, a1, col, w) => {
+		let at = |a: f32| c + Vec2::new(a.cos(), a.sin()) * r;
+		let (p, q) = (at(a0), at(a1));
+		writeln!(s, "<path d=\"M {} {} A {} {} 0 {} 1 {} {}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>",
+			p.x, p.y, r, r, (a1 - a0 > PI) as u8, q.x, q.y, paint(col), w).unwrap();
+	},
+	DrawCmd::Circle(c, r, col, w) =>
+		writeln!(s, "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>", c.x, c.y, r, paint(col), w).unwrap(),
+	DrawCmd::CircleFilled(c, r, col) =>
+		writeln!(s, "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>", c.x, c.y, r, paint(col)).unwrap(),
+	DrawCmd::Line(a, b, col, w) =>
+		writeln!(s, "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\"/>", a.x, a.y, b.x, b.y, paint(col), w).unwrap(),
+	DrawCmd::Rect(a, b, col, w) =>
+		writeln!(s, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\"/>",
+			a.x.min(b.x), a.y.min(b.y), (b.x - a.x).abs(), (b.y - a.y).abs(), paint(col), w).unwrap(),
+	DrawCmd::RectFilled(a, b, col) =>
+		writeln!(s, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+			a.x.min(b.x), a.y.min(b.y), (b.x - a.x).abs(), (b.y - a.y).abs(), paint(col)).unwrap(),
+	DrawCmd::TriangleFilled(a, b, c, col) =>
+		writeln!(s, "<polygon points=\"{},{} {},{} {},{}\" fill=\"{}\"/>", a.x, a.y, b.x, b.y, c.x, c.y, paint(col)).unwrap(),
+	DrawCmd::Text(p, col, ref t) => {
+		let d = ts(t);
+		writeln!(s, "<text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\" dominant-baseline=\"hanging\" fill=\"{}\">{}</text>",
+			p.x, p.y, d.y, d.x, paint(col), escape(t)).unwrap();
+	},
+	}
+}
+
+pub fn write(ts: &Measure, l: &[DrawCmd], scale: f32) -> String {
+	let (lo, hi) = bounds(ts, l);
+	let (lo, hi) = (lo - Vec2::new(MARGIN, MARGIN), hi + Vec2::new(MARGIN, MARGIN));
+	let (w, h) = (hi.x - lo.x, hi.y - lo.y);
+	let mut s = String::new();
+	writeln!(s, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+	writeln!(s, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}mm\" height=\"{}mm\" viewBox=\"{} {} {} {}\">",
+		w * scale, h * scale, lo.x, lo.y, w, h).unwrap();
+	for d in l {
+		elem(ts, &mut s, d);
+	}
+	writeln!(s, "</svg>").unwrap();
+	s
+}
+
+pub fn save(path: &str, ts: &Measure, l: &[DrawCmd], scale: f32) -> Result<(), String> {
+	fs::write(path, write(ts, l, scale)).map_err(|e| format!("{}: {}", path, e))
+}
+
+const FLATTEN : usize = 16;
+pub const EPS : f32 = 1e-4;
+
+fn attrs(tag: &str) -> Vec<(&str, &str)> {
+	let mut r = Vec::new();
+	let mut s = tag;
+	while let Some(i) = s.find('=') {
+		let name = s[..i].split_whitespace().last().unwrap_or("");
+		let rest = s[i + 1..].trim_start();
+		let q = match rest.chars().next() {
+			Some(q) if q == '"' || q == '\'' => q,
+			_ => break,
+		};
+		let rest = &rest[1..];
+		let j = match rest.find(q) {
+			Some(j) => j,
+			None => break,
+		};
+		r.push((name, &rest[..j]));
+		s = &rest[j + 1..];
+	}
+	r
+}
+
+type Tag<'a> = (&'a str, Vec<(&'a str, &'a str)>, bool);
+
+fn tags(s: &str) -> Vec<Tag<'_>> {
+	let mut r = Vec::new();
+	for t in s.split('<').skip(1) {
+		let t = match t.find('>') {
+			Some(i) => &t[..i],
+			None => continue,
+		};
+		let close = t.starts_with('/');
+		let t = t.trim_start_matches('/');
+		let name = t.split(|c: char| c.is_whitespace() || c == '/').next().unwrap_or("");
+		if close {
+			r.push((name, vec![], true));
+		} else if !t.ends_with('/') || name != "g" {
+			r.push((name, attrs(&t[name.len()..]), false));
+		}
+	}
+	r
+}
+
+type Xform = [f32; 6];
+
+const IDENTITY : Xform = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+fn mul(m: &Xform, n: &Xform) -> Xform {
+	[
+		m[0] * n[0] + m[2] * n[1],
+		m[1] * n[0] + m[3] * n[1],
+		m[0] * n[2] + m[2] * n[3],
+		m[1] * n[2] + m[3] * n[3],
+		m[0] * n[4] + m[2] * n[5] + m[4],
+		m[1] * n[4] + m[3] * n[5] + m[5],
+	]
+}
+
+fn apply(m: &Xform, p: Vec2) -> Vec2 {
+	Vec2::new(m[0] * p.x + m[2] * p.y + m[4], m[1] * p.x + m[3] * p.y + m[5])
+}
+
+fn transform(s: &str) -> Result<Xform, String> {
+	let mut m = IDENTITY;
+	let mut s = s;
+	while let Some(i) = s.find('(') {
+		let name = s[..i].trim_matches(|c: char| c.is_whitespace() || c == ',');
+		let j = s.find(')').ok_or_else(|| format!("unterminated transform '{}'", name))?;
+		let v = numbers(&s[i + 1..j])?;
+		s = &s[j + 1..];
+		let rot = |a: f32| {
+			let (sn, cs) = a.to_radians().sin_cos();
+			[cs, sn, -sn, cs, 0.0, 0.0]
+		};
+		let t = match (name, &v[..]) {
+		("matrix", &[a, b, c, d, e, f]) => [a, b, c, d, e, f],
+		("translate", &[x]) => [1.0, 0.0, 0.0, 1.0, x, 0.0],
+		("translate", &[x, y]) => [1.0, 0.0, 0.0, 1.0, x, y],
+		("scale", &[x]) => [x, 0.0, 0.0, x, 0.0, 0.0],
+		("scale", &[x, y]) => [x, 0.0, 0.0, y, 0.0, 0.0],
+		("rotate", &[a]) => rot(a),
+		("rotate", &[a, x, y]) => mul(&mul(&[1.0, 0.0, 0.0, 1.0, x, y], &rot(a)), &[1.0, 0.0, 0.0, 1.0, -x, -y]),
+		("skewX", &[a]) => [1.0, 0.0, a.to_radians().tan(), 1.0, 0.0, 0.0],
+		("skewY", &[a]) => [1.0, a.to_radians().tan(), 0.0, 1.0, 0.0, 0.0],
+		_ => return Err(format!("bad transform '{}'", name)),
+		};
+		m = mul(&m, &t);
+	}
+	Ok(m)
+}
+
+fn numbers(s: &str) -> Result<Vec<f32>, String> {
+	let cs : Vec<char> = s.chars().collect();
+	let mut r = Vec::new();
+	let mut i = 0;
+	while i < cs.len() {
+		if cs[i].is_whitespace() || cs[i] == ',' {
+			i += 1;
+			continue;
+		}
+		let j = i;
+		if cs[i] == '-' || cs[i] == '+' {
+			i += 1;
+		}
+		let mut dot = false;
+		while i < cs.len() && (cs[i].is_ascii_digit() || cs[i] == '.' && !dot) {
+			dot |= cs[i] == '.';
+			i += 1;
+		}
+		if i < cs.len() && (cs[i] == 'e' || cs[i] == 'E') {
+			i += 1;
+			if i < cs.len() && (cs[i] == '-' || cs[i] == '+') {
+				i += 1;
+			}
+			while i < cs.len() && cs[i].is_ascii_digit() {
+				i += 1;
+			}
+		}
+		let n : String = cs[j..i].iter().collect();
+		match n.parse() {
+			Ok(v) => r.push(v),
+			Err(_) => return Err(format!("bad number '{}'", if n.is_empty() { cs[i].to_string() } else { n })),
+		}
+	}
+	Ok(r)
+}
+
+fn attr(a: &[(&str, &str)], name: &str) -> Result<f32, String> {
+	match a.iter().find(|x| x.0 == name) {
+	Some(x) => x.1.trim().parse().map_err(|_| format!("bad {} '{}'", name, x.1)),
+	None => Ok(0.0),
+	}
+}
+
+fn pairs(v: &[f32]) -> Vec<Vec2> {
+	v.chunks(2).filter(|c| c.len() == 2).map(|c| Vec2::new(c[0], c[1])).collect()
+}
+
+fn cubic(l: &mut Vec<Vec2>, p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2) {
+	for i in 1..=FLATTEN {
+		let t = i as f32 / FLATTEN as f32;
+		let u = 1.0 - t;
+		l.push(p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t));
+	}
+}
+
+fn quad(l: &mut Vec<Vec2>, p0: Vec2, p1: Vec2, p2: Vec2) {
+	for i in 1..=FLATTEN {
+		let t = i as f32 / FLATTEN as f32;
+		let u = 1.0 - t;
+		l.push(p0 * (u * u) + p1 * (2.0 * u * t) + p2 * (t * t));
+	}
+}
+
+fn arc(l: &mut Vec<Vec2>, p0: Vec2, a: &[f32], p1: Vec2) {
+	let (mut rx, mut ry) = (a[0].abs(), a[1].abs());
+	if rx == 0.0 || ry == 0.0 {
+		l.push(p1);
+		return;
+	}
+	let (sn, cs) = a[2].to_radians().sin_cos();
+	let d = (p0 - p1) * 0.5;
+	let x1 = cs * d.x + sn * d.y;
+	let y1 = -sn * d.x + cs * d.y;
+	let k = x1 * x1 / (rx * rx) + y1 * y1 / (ry * ry);
+	if k > 1.0 {
+		rx *= k.sqrt();
+		ry *= k.sqrt();
+	}
+	let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+	let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+	let mut f = if den > 0.0 { (num / den).max(0.0).sqrt() } else { 0.0 };
+	let sweep = a[4] != 0.0;
+	if (a[3] != 0.0) == sweep {
+		f = -f;
+	}
+	let (cx, cy) = (f * rx * y1 / ry, -f * ry * x1 / rx);
+	let c = (p0 + p1) * 0.5 + Vec2::new(cs * cx - sn * cy, sn * cx + cs * cy);
+	let t0 = ((y1 - cy) / ry).atan2((x1 - cx) / rx);
+	let t1 = ((-y1 - cy) / ry).atan2((-x1 - cx) / rx);
+	let mut dt = t1 - t0;
+	if !sweep && dt > 0.0 {
+		dt -= 2.0 * PI;
+	} else if sweep && dt < 0.0 {
+		dt += 2.0 * PI;
+	}
+	for i in 1..FLATTEN {
+		let (y, x) = (t0 + dt * i as f32 / FLATTEN as f32).sin_cos();
+		let (x, y) = (rx * x, ry * y);
+		l.push(c + Vec2::new(cs * x - sn * y, sn * x + cs * y));
+	}
+	l.push(p1);
+}
+
+fn path(d: &str) -> Result<Vec<Vec<Vec2>>, String> {
+	let mut r : Vec<Vec<Vec2>> = Vec::new();
+	let mut cur = Vec2::zero();
+	let mut start = Vec2::zero();
+	let mut ctrl = Vec2::zero();
+	let mut last = ' ';
+	let mut s = d;
+	while let Some(i) = s.find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E') {
+		let cmd = s[i..].chars().next().unwrap();
+		let rest = &s[i + 1..];
+		let j = rest.find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E').unwrap_or(rest.len());
+		let v = numbers(&rest[..j])?;
+		s = &rest[j..];
+		let rel = cmd.is_ascii_lowercase();
+		let n = match cmd.to_ascii_uppercase() {
+			'M' | 'L' | 'T' => 2,
+			'H' | 'V' => 1,
+			'C' => 6,
+			'S' | 'Q' => 4,
+			'A' => 7,
+			'Z' => 0,
+			c => return Err(format!("unknown path command '{}'", c)),
+		};
+		if n == 0 {
+			if let Some(l) = r.last_mut() {
+				l.push(start);
+			}
+			cur = start;
+			last = 'Z';
+			continue;
+		}
+		if v.is_empty() || v.len() % n != 0 {
+			return Err(format!("wrong number of arguments to '{}'", cmd));
+		}
+		for (k, a) in v.chunks(n).enumerate() {
+			let o = if rel { cur } else { Vec2::zero() };
+			let pt = |i: usize| o + Vec2::new(a[i], a[i + 1]);
+			let mut c = cmd.to_ascii_uppercase();
+			if c == 'M' && k > 0 {
+				c = 'L';
+			}
+			if c == 'M' || r.is_empty() {
+				r.push(vec![if c == 'M' { pt(0) } else { cur }]);
+			}
+			let l = r.last_mut().unwrap();
+			let refl = cur * 2.0 - ctrl;
+			match c {
+			'M' => {
+				cur = pt(0);
+				start = cur;
+			},
+			'L' => {
+				cur = pt(0);
+				l.push(cur);
+			},
+			'H' => {
+				cur = Vec2::new(a[0] + if rel { cur.x } else { 0.0 }, cur.y);
+				l.push(cur);
+			},
+			'V' => {
+				cur = Vec2::new(cur.x, a[0] + if rel { cur.y } else { 0.0 });
+				l.push(cur);
+			},
+			'C' => {
+				ctrl = pt(2);
+				cubic(l, cur, pt(0), ctrl, pt(4));
+				cur = pt(4);
+			},
+			'S' => {
+				let c1 = if "CcSs".contains(last) { refl } else { cur };
+				ctrl = pt(0);
+				cubic(l, cur, c1, ctrl, pt(2));
+				cur = pt(2);
+			},
+			'Q' => {
+				ctrl = pt(0);
+				quad(l, cur, ctrl, pt(2));
+				cur = pt(2);
+			},
+			'T' => {
+				ctrl = if "QqTt".contains(last) { refl } else { cur };
+				quad(l, cur, ctrl, pt(0));
+				cur = pt(0);
+			},
+			_ => {
+				arc(l, cur, a, pt(5));
+				cur = pt(5);
+			},
+			}
+			last = cmd;
+		}
+	}
+	Ok(r)
+}
+
+pub fn read(s: &str) -> Result<Vec<Vec<Vec2>>, String> {
+	let mut r = Vec::new();
+	let mut stack = vec![IDENTITY];
+	for (name, a, close) in tags(s) {
+		let m = match a.iter().find(|x| x.0 == "transform") {
+			Some(x) => mul(stack.last().unwrap(), &transform(x.1)?),
+			None => *stack.last().unwrap(),
+		};
+		let n = r.len();
+		match name {
+		"g" if close && stack.len() > 1 => {
+			stack.pop();
+		},
+		"g" if close => (),
+		"g" => stack.push(m),
+		"line" => r.push(vec![
+			Vec2::new(attr(&a, "x1")?, attr(&a, "y1")?),
+			Vec2::new(attr(&a, "x2")?, attr(&a, "y2")?),
+		]),
+		"polyline" | "polygon" => {
+			let mut l = pairs(&numbers(a.iter().find(|x| x.0 == "points").map_or("", |x| x.1))?);
+			if name == "polygon" && l.len() > 2 {
+				let p = l[0];
+				l.push(p);
+			}
+			r.push(l);
+		},
+		"path" => r.extend(path(a.iter().find(|x| x.0 == "d").map_or("", |x| x.1))?),
+		_ => (),
+		}
+		for l in &mut r[n..] {
+			for p in l.iter_mut() {
+				*p = apply(&m, *p);
+			}
+			l.dedup_by(|a, b| a.dist(*b) < EPS);
+		}
+	}
+	r.retain(|l| l.len() > 1);
+	Ok(r)
+}
+
+pub fn load(path: &str) -> Result<Vec<Vec<Vec2>>, String> {
+	let s = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+	read(&s).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn near(a: Vec2, b: Vec2) -> bool {
+		a.dist(b) < 1e-3
+	}
+
+	#[test]
+	fn group_transforms() {
+		let r = read(r#"<svg><g transform="translate(10 20)"><g transform="scale(2)">
+			<line x1="0" y1="0" x2="1" y2="0" transform="rotate(90)"/></g>
+			<line x1="0" y1="0" x2="1" y2="1"/></g><line x1="0" y1="0" x2="1" y2="1"/></svg>"#).unwrap();
+		assert_eq!(r.len(), 3);
+		assert!(near(r[0][0], Vec2::new(10.0, 20.0)) && near(r[0][1], Vec2::new(10.0, 22.0)));
+		assert!(near(r[1][1], Vec2::new(11.0, 21.0)));
+		assert!(near(r[2][1], Vec2::new(1.0, 1.0)));
+	}
+
+	#[test]
+	fn arc_flattened() {
+		let r = read(r#"<path d="M 0 0 A 5 5 0 0 1 10 0"/>"#).unwrap();
+		assert_eq!(r[0].len(), FLATTEN + 1);
+		assert!(near(*r[0].last().unwrap(), Vec2::new(10.0, 0.0)));
+		let c = Vec2::new(5.0, 0.0);
+		assert!(r[0].iter().all(|p| (p.dist(c) - 5.0).abs() < 1e-3));
+		assert!(r[0].iter().all(|p| p.y <= 1e-3));
+	}
+
+	#[test]
+	fn skips_zero_length() {
+		let r = read(r#"<path d="M 0 0 L 0 0 L 5 0 L 5 0 Z"/><line x1="1" y1="1" x2="1" y2="1"/>"#).unwrap();
+		assert_eq!(r, vec![vec![Vec2::new(0.0, 0.0), Vec2::new(5.0, 0.0), Vec2::new(0.0, 0.0)]]);
+	}
+}
@@ -0,0 +1,60 @@
+use dat::*;
+use ent::*;
+
+const MIN_ZOOM : f32 = 0.01;
+const MAX_ZOOM : f32 = 100.0;
+
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub struct View {
+	pub pan: Vec2,
+	pub zoom: f32,
+}
+impl View {
+	pub fn new() -> View {
+		View { pan: Vec2::zero(), zoom: 1.0 }
+	}
+	pub fn screen(&self, p: Vec2) -> Vec2 {
+		p * self.zoom + self.pan
+	}
+	pub fn world(&self, p: Vec2) -> Vec2 {
+		(p - self.pan) * (1.0 / self.zoom)
+	}
+	pub fn zoom_at(&mut self, s: Vec2, f: f32) {
+		let w = self.world(s);
+		self.zoom = (self.zoom * f).clamp(MIN_ZOOM, MAX_ZOOM);
+		self.pan = s - w * self.zoom;
+	}
+	pub fn fit(&mut self, sk: &Sketch, size: Vec2, margin: f32) {
+		let mut pts : Vec<Vec2> = sk.points.iter().map(|(_, &p)| p).collect();
+		for (_, c) in sk.circles.iter() {
+			let p = sk.points[c.c];
+			pts.extend(&[p - Vec2::new(c.r, c.r), p + Vec2::new(c.r, c.r)]);
+		}
+		for (_, a) in sk.arcs.iter() {
+			let (p, r) = (sk.points[a.c], a.radius(&sk.points));
+			pts.extend(&[p - Vec2::new(r, r), p + Vec2::new(r, r)]);
+		}
+		if pts.is_empty() {
+			*self = View::new();
+			return;
+		}
+		let mut lo = pts[0];
+		let mut hi = pts[0];
+		for p in pts {
+			lo = Vec2::new(lo.x.min(p.x), lo.y.min(p.y));
+			hi = Vec2::new(hi.x.max(p.x), hi.y.max(p.y));
+		}
+		let d = hi - lo;
+		let avail = Vec2::new(size.x - 2.0 * margin, size.y - 2.0 * margin);
+		self.zoom = if d.x <= 0.0 && d.y <= 0.0 {
+			1.0
+		} else {
+			(avail.x / d.x).min(avail.y / d.y).clamp(MIN_ZOOM, MAX_ZOOM)
+		};
+		self.pan = size * 0.5 - (lo + hi) * 0.5 * self.zoom;
+	}
+}
+
+pub fn snap(p: Vec2, step: f32) -> Vec2 {
+	Vec2::new((p.x / step).round() * step, (p.y / step).round() * step)
+}